@@ -1,9 +1,9 @@
 extern crate criterion;
 extern crate exponential_decay_histogram;
 
-use criterion::{Bencher, Criterion};
-use exponential_decay_histogram::ExponentialDecayHistogram;
-use std::time::Instant;
+use criterion::{BatchSize, Bencher, Criterion};
+use exponential_decay_histogram::{ExponentialDecayHistogram, Snapshot};
+use std::time::{Duration, Instant};
 
 fn update(b: &mut Bencher) {
     let mut histogram = ExponentialDecayHistogram::new();
@@ -30,6 +30,57 @@ fn update_at(b: &mut Bencher) {
     criterion::black_box(histogram.snapshot());
 }
 
+// measures the update which crosses the rescale threshold
+fn rescale(b: &mut Bencher) {
+    let now = Instant::now();
+
+    b.iter_batched_ref(
+        || {
+            let mut histogram = ExponentialDecayHistogram::builder()
+                .at(now)
+                .rescale_threshold(Duration::from_secs(60))
+                .build();
+
+            for i in 0..1028 {
+                histogram.update_at(now + Duration::from_millis(i as u64), i);
+            }
+
+            histogram
+        },
+        |histogram| histogram.update_at(now + Duration::from_secs(61), 0),
+        BatchSize::SmallInput,
+    );
+}
+
+fn warm_up(b: &mut Bencher) {
+    let now = Instant::now();
+
+    b.iter(|| {
+        let mut histogram = ExponentialDecayHistogram::builder().at(now).build();
+
+        for i in 0..1028 {
+            histogram.update_at(now, i);
+        }
+
+        histogram
+    });
+}
+
+fn warm_up_reserved(b: &mut Bencher) {
+    let now = Instant::now();
+
+    b.iter(|| {
+        let mut histogram = ExponentialDecayHistogram::builder().at(now).build();
+        histogram.reserve();
+
+        for i in 0..1028 {
+            histogram.update_at(now, i);
+        }
+
+        histogram
+    });
+}
+
 fn snapshot(b: &mut Bencher) {
     let mut histogram = ExponentialDecayHistogram::new();
 
@@ -40,8 +91,57 @@ fn snapshot(b: &mut Bencher) {
     b.iter(|| histogram.snapshot());
 }
 
+fn snapshot_into(b: &mut Bencher) {
+    let mut histogram = ExponentialDecayHistogram::new();
+
+    for i in 0..1028 {
+        histogram.update(i);
+    }
+
+    let mut snapshot = histogram.snapshot();
+    b.iter(|| histogram.snapshot_into(&mut snapshot));
+}
+
+fn export(b: &mut Bencher) {
+    let mut histogram = ExponentialDecayHistogram::new();
+
+    for i in 0..1028 {
+        histogram.update(i);
+    }
+
+    let snapshot = histogram.snapshot();
+    b.iter(|| {
+        (
+            snapshot.mean(),
+            snapshot.stddev(),
+            snapshot.quantiles(&[0.5, 0.75, 0.95, 0.99, 0.999]),
+        )
+    });
+}
+
+fn query_snapshot(size: usize) -> Snapshot {
+    let mut histogram = ExponentialDecayHistogram::builder().size(size).build();
+
+    for i in 0..size as i64 {
+        histogram.update(i);
+    }
+
+    histogram.snapshot()
+}
+
+fn value(b: &mut Bencher, size: usize, quantile: f64) {
+    let snapshot = query_snapshot(size);
+    b.iter(|| snapshot.value(criterion::black_box(quantile)));
+}
+
+fn quantiles(b: &mut Bencher, size: usize) {
+    let snapshot = query_snapshot(size);
+    let quantiles = [0.5, 0.75, 0.9, 0.95, 0.99, 0.999];
+    b.iter(|| snapshot.quantiles(criterion::black_box(&quantiles)));
+}
+
 fn now(b: &mut Bencher) {
-    b.iter(|| Instant::now());
+    b.iter(Instant::now);
 }
 
 fn main() {
@@ -49,7 +149,18 @@ fn main() {
         .configure_from_args()
         .bench_function("update", update)
         .bench_function("update_at", update_at)
+        .bench_function("rescale", rescale)
+        .bench_function("warm_up", warm_up)
+        .bench_function("warm_up_reserved", warm_up_reserved)
         .bench_function("snapshot", snapshot)
+        .bench_function("snapshot_into", snapshot_into)
+        .bench_function("export", export)
+        .bench_function("value_p50/16", |b| value(b, 16, 0.5))
+        .bench_function("value_p50/1028", |b| value(b, 1028, 0.5))
+        .bench_function("value_p99/16", |b| value(b, 16, 0.99))
+        .bench_function("value_p99/1028", |b| value(b, 1028, 0.99))
+        .bench_function("quantiles/16", |b| quantiles(b, 16))
+        .bench_function("quantiles/1028", |b| quantiles(b, 1028))
         .bench_function("now", now)
         .final_summary();
 }
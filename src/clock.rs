@@ -0,0 +1,128 @@
+use core::fmt;
+#[cfg(not(feature = "std"))]
+use core::ops::{Add, AddAssign, Sub};
+#[cfg(not(feature = "std"))]
+use core::time::Duration;
+
+#[cfg(feature = "std")]
+pub use std::time::Instant;
+
+/// A source of the current time for an [`ExponentialDecayHistogram`].
+///
+/// The histogram consults its clock in methods like [`ExponentialDecayHistogram::update`] which
+/// operate at the "current" time. A custom clock can be used to control the passage of time in
+/// tests.
+///
+/// [`ExponentialDecayHistogram`]: crate::ExponentialDecayHistogram
+/// [`ExponentialDecayHistogram::update`]: crate::ExponentialDecayHistogram::update
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+}
+
+/// A [`Clock`] which uses the system's monotonic clock.
+///
+/// This is the default clock.
+///
+/// Requires the `std` Cargo feature.
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// Without the standard library there is no system clock to fall back to, so the default clock
+// requires the user to either configure a clock or pass times explicitly.
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct MissingClock;
+
+#[cfg(not(feature = "std"))]
+impl Clock for MissingClock {
+    fn now(&self) -> Instant {
+        panic!(
+            "no clock is configured for the histogram; use `Builder::clock` or the `*_at` methods"
+        )
+    }
+}
+
+/// A point in time, measured as the time elapsed since an arbitrary epoch.
+///
+/// This takes the place of `std::time::Instant` when the `std` feature is disabled. The epoch and
+/// tick rate are up to the [`Clock`] producing the instants - all the histogram requires is that
+/// instants from the same source are monotonic.
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Instant(Duration);
+
+#[cfg(not(feature = "std"))]
+impl Instant {
+    /// Returns the instant a specified amount of time after the epoch.
+    pub const fn from_elapsed(elapsed: Duration) -> Instant {
+        Instant(elapsed)
+    }
+
+    /// Returns the amount of time elapsed between the epoch and this instant.
+    pub const fn elapsed(&self) -> Duration {
+        self.0
+    }
+
+    /// Returns the amount of time elapsed from another instant to this one, or zero if that
+    /// instant is later than this one.
+    pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    /// Returns the amount of time elapsed from another instant to this one, or `None` if that
+    /// instant is later than this one.
+    pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+        self.0.checked_sub(earlier.0)
+    }
+
+    /// Returns `Some(t)` where `t` is the time `self + duration` if `t` can be represented, and
+    /// `None` otherwise.
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_add(duration).map(Instant)
+    }
+
+    /// Returns `Some(t)` where `t` is the time `self - duration` if `t` can be represented, and
+    /// `None` otherwise.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_sub(duration).map(Instant)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    /// # Panics
+    ///
+    /// Panics if the resulting instant overflows.
+    fn add(self, other: Duration) -> Instant {
+        self.checked_add(other)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, other: Duration) {
+        *self = *self + other;
+    }
+}
+
+/// Like `std::time::Instant`, this saturates to zero if `other` is later than `self`.
+#[cfg(not(feature = "std"))]
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, other: Instant) -> Duration {
+        self.saturating_duration_since(other)
+    }
+}
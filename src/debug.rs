@@ -0,0 +1,92 @@
+use crate::{ExponentialDecayHistogram, Value};
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::fmt::{Debug, Write};
+use rand::Rng;
+
+impl<T, R> ExponentialDecayHistogram<T, R>
+where
+    T: Value + Debug,
+    R: Rng,
+{
+    /// Renders the histogram's internal reservoir as a human-readable table.
+    ///
+    /// The first line reports the histogram's decay landmark, count, and number of retained
+    /// samples. It is followed by a header and a line for each retained sample with its priority,
+    /// value, and weight, in descending order of priority. The sample with the lowest priority is
+    /// the next to be evicted.
+    ///
+    /// This is intended only as a diagnostic aid, and the format is not stable.
+    ///
+    /// Requires the `debug` Cargo feature.
+    pub fn debug_dump(&self) -> String {
+        let mut samples = self.values.iter().collect::<Vec<_>>();
+        samples.sort_by_key(|s| Reverse(s.priority));
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            "landmark={:?} count={} samples={}",
+            self.start_time,
+            self.count,
+            samples.len()
+        )
+        .unwrap();
+        writeln!(out, "{:>14} {:>14} value", "priority", "weight").unwrap();
+        for sample in samples {
+            writeln!(
+                out,
+                "{:>14.6e} {:>14.6e} {:?}",
+                *sample.priority, sample.weight, sample.value
+            )
+            .unwrap();
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ExponentialDecayHistogram;
+    use std::time::Duration;
+
+    #[test]
+    fn debug_dump() {
+        let mut histogram = ExponentialDecayHistogram::builder().size(10).build();
+        let mut now = histogram.start_time();
+
+        for i in 0..100 {
+            histogram.update_at(now, i);
+            now += Duration::from_secs(1);
+        }
+
+        let dump = histogram.debug_dump();
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2 + 10);
+        assert!(lines[0].contains("count=100 samples=10"));
+
+        let mut values = lines[2..]
+            .iter()
+            .map(|line| {
+                line.split_whitespace()
+                    .last()
+                    .unwrap()
+                    .parse::<i64>()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        values.sort_unstable();
+        assert_eq!(
+            values,
+            histogram
+                .snapshot()
+                .values()
+                .map(|(v, _)| v)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{weighted_mean, Snapshot, SnapshotEntry};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt;
+use ordered_float::NotNan;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 1 + 8 + 8 + 8;
+const ENTRY_LEN: usize = 8 + 8 + 8;
+
+/// Methods for encoding snapshots in a compact binary format.
+///
+/// The format consists of a version byte followed by the snapshot's count, its total weight, and
+/// the number of values. Each value is then stored along with its normalized weight and the
+/// quantile at which it starts. Integers and floats are stored in 8 byte little endian form.
+impl Snapshot<i64> {
+    /// Encodes the snapshot into bytes which can be decoded by [`Snapshot::decode`].
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(HEADER_LEN + self.entries.len() * ENTRY_LEN);
+
+        buf.push(VERSION);
+        buf.extend_from_slice(&self.count.to_le_bytes());
+        buf.extend_from_slice(&self.total_weight.to_le_bytes());
+        buf.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        for entry in &self.entries {
+            buf.extend_from_slice(&entry.value.to_le_bytes());
+            buf.extend_from_slice(&entry.norm_weight.to_le_bytes());
+            buf.extend_from_slice(&entry.quantile.to_le_bytes());
+        }
+
+        buf
+    }
+
+    /// Decodes a snapshot from bytes produced by [`Snapshot::encode`].
+    pub fn decode(bytes: &[u8]) -> Result<Snapshot<i64>, DecodeError> {
+        let mut reader = Reader(bytes);
+
+        let version = reader.read(|[b]: [u8; 1]| b)?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+
+        let count = reader.read(u64::from_le_bytes)?;
+        let total_weight = reader.read(f64::from_le_bytes)?;
+        let len = reader.read(u64::from_le_bytes)?;
+
+        // don't trust the length for the allocation size
+        let mut entries = Vec::with_capacity(bytes.len() / ENTRY_LEN);
+        for _ in 0..len {
+            let value = reader.read(i64::from_le_bytes)?;
+            let norm_weight = reader.read(f64::from_le_bytes)?;
+            let quantile = reader.read(f64::from_le_bytes)?;
+
+            if !(0. ..=1.).contains(&norm_weight) {
+                return Err(DecodeError::Malformed);
+            }
+            let quantile = match NotNan::new(quantile) {
+                Ok(quantile) if (0. ..=1.).contains(&*quantile) => quantile,
+                _ => return Err(DecodeError::Malformed),
+            };
+            if entries
+                .last()
+                .is_some_and(|e: &SnapshotEntry<i64>| e.value > value || e.quantile > quantile)
+            {
+                return Err(DecodeError::Malformed);
+            }
+
+            entries.push(SnapshotEntry {
+                value,
+                norm_weight,
+                quantile,
+            });
+        }
+
+        if !reader.0.is_empty() {
+            return Err(DecodeError::Malformed);
+        }
+
+        let mean = weighted_mean(entries.iter().map(|e| (e.value, e.norm_weight)));
+
+        Ok(Snapshot {
+            entries,
+            count,
+            mean,
+            total_weight,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn read<const N: usize, T>(&mut self, f: impl FnOnce([u8; N]) -> T) -> Result<T, DecodeError> {
+        if self.0.len() < N {
+            return Err(DecodeError::Truncated);
+        }
+
+        let (head, tail) = self.0.split_at(N);
+        self.0 = tail;
+        Ok(f(head.try_into().unwrap()))
+    }
+}
+
+/// An error returned when decoding a [`Snapshot`] from bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before the snapshot was fully decoded.
+    Truncated,
+    /// The input was encoded with an unsupported version of the format.
+    UnsupportedVersion(u8),
+    /// The input contained invalid weights or quantiles, unsorted values, or trailing bytes.
+    Malformed,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => fmt.write_str("encoded snapshot is truncated"),
+            DecodeError::UnsupportedVersion(version) => {
+                write!(fmt, "unsupported snapshot encoding version {}", version)
+            }
+            DecodeError::Malformed => fmt.write_str("encoded snapshot is malformed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for DecodeError {}
+
+#[cfg(test)]
+mod test {
+    use crate::{DecodeError, ExponentialDecayHistogram, Snapshot};
+    use std::time::Duration;
+
+    #[test]
+    fn round_trip() {
+        let mut histogram = ExponentialDecayHistogram::builder().size(100).build();
+        let mut now = histogram.start_time();
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(Snapshot::decode(&snapshot.encode()).unwrap(), snapshot);
+
+        for i in 0..1000 {
+            histogram.update_at(now, i);
+            now += Duration::from_millis(100);
+        }
+
+        let snapshot = histogram.snapshot();
+        let encoded = snapshot.encode();
+        assert_eq!(encoded.len(), 25 + 100 * 24);
+
+        let decoded = Snapshot::decode(&encoded).unwrap();
+        assert_eq!(decoded, snapshot);
+        assert_eq!(decoded.count(), snapshot.count());
+        assert_eq!(decoded.total_weight(), snapshot.total_weight());
+        assert_eq!(decoded.mean(), snapshot.mean());
+        assert_eq!(decoded.stddev(), snapshot.stddev());
+        assert_eq!(decoded.value(0.99), snapshot.value(0.99));
+    }
+
+    #[test]
+    fn invalid() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        histogram.update(1);
+        histogram.update(2);
+        let encoded = histogram.snapshot().encode();
+
+        assert_eq!(Snapshot::decode(&[]), Err(DecodeError::Truncated));
+        assert_eq!(
+            Snapshot::decode(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut bad = encoded.clone();
+        bad[0] = 2;
+        assert_eq!(
+            Snapshot::decode(&bad),
+            Err(DecodeError::UnsupportedVersion(2))
+        );
+
+        let mut bad = encoded.clone();
+        bad.push(0);
+        assert_eq!(Snapshot::decode(&bad), Err(DecodeError::Malformed));
+
+        // swap the order of the two values
+        let mut bad = encoded.clone();
+        bad[25..33].copy_from_slice(&2i64.to_le_bytes());
+        bad[49..57].copy_from_slice(&1i64.to_le_bytes());
+        assert_eq!(Snapshot::decode(&bad), Err(DecodeError::Malformed));
+
+        let mut bad = encoded;
+        bad[33..41].copy_from_slice(&f64::NAN.to_le_bytes());
+        assert_eq!(Snapshot::decode(&bad), Err(DecodeError::Malformed));
+    }
+}
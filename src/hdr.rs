@@ -0,0 +1,68 @@
+use crate::{Snapshot, Value};
+use hdrhistogram::Histogram;
+
+// The total count the weights of a snapshot's values are scaled to.
+const HDR_SCALE: f64 = 1_000_000.;
+
+impl<T> Snapshot<T>
+where
+    T: Value,
+{
+    /// Converts the snapshot into an HDR histogram.
+    ///
+    /// The HDR histogram tracks 3 significant figures and automatically resizes to fit the values
+    /// in the snapshot. Each distinct value is recorded `round(weight * 1,000,000)` times, where
+    /// `weight` is its normalized weight, so the weights of the values are preserved to a
+    /// resolution of one millionth. Values with a smaller weight are not recorded. Values are
+    /// converted through `f64`, and negative values are clamped to 0.
+    ///
+    /// Requires the `hdrhistogram` Cargo feature.
+    pub fn to_hdr(&self) -> Histogram<u64> {
+        let mut histogram = Histogram::new(3).unwrap();
+
+        for (value, weight) in self.values() {
+            let count = (weight * HDR_SCALE).round() as u64;
+            if count > 0 {
+                histogram
+                    .record_n(value.to_f64().max(0.) as u64, count)
+                    .unwrap();
+            }
+        }
+
+        histogram
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ExponentialDecayHistogram;
+    use std::time::Duration;
+
+    #[test]
+    fn to_hdr() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let mut now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().to_hdr().len(), 0);
+
+        for i in 0..10_000 {
+            histogram.update_at(now, i);
+            now += Duration::from_millis(10);
+        }
+
+        let snapshot = histogram.snapshot();
+        let hdr = snapshot.to_hdr();
+
+        let median = snapshot.value(0.5) as f64;
+        assert!((hdr.value_at_quantile(0.5) as f64 - median).abs() / median < 0.01);
+        assert!((hdr.len() as f64 - 1_000_000.).abs() < 1_000.);
+
+        let mut histogram = ExponentialDecayHistogram::new();
+        histogram.update_at(now, -5);
+        histogram.update_at(now, 5);
+
+        let hdr = histogram.snapshot().to_hdr();
+        assert_eq!(hdr.min(), 0);
+        assert_eq!(hdr.max(), 5);
+    }
+}
@@ -42,56 +42,297 @@
 //! println!("median: {}", snapshot.value(0.5));
 //! println!("99th percentile: {}", snapshot.value(0.99));
 //! ```
+//!
+//! # `no_std` support
+//!
+//! The crate depends on the standard library by default, but only requires `alloc` when the `std`
+//! Cargo feature is disabled and the `alloc` feature is enabled. In that configuration, times are
+//! represented by the crate's own [`Instant`] type rather than `std::time::Instant`, and there is
+//! no default [`Clock`] - one must be configured with [`Builder::clock`], or times must be passed
+//! explicitly to methods like [`ExponentialDecayHistogram::update_at`].
 #![warn(missing_docs)]
-use ordered_float::NotNan;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("either the `std` or `alloc` feature must be enabled");
+
+extern crate alloc;
+
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::fmt;
+use core::iter;
+use core::mem;
+use core::slice;
+use core::time::Duration;
+pub use ordered_float::NotNan;
 use rand::distr::Open01;
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
-use std::collections::BTreeMap;
-use std::iter;
-use std::slice;
-use std::time::{Duration, Instant};
+#[cfg(feature = "std")]
+use std::error::Error;
+
+#[cfg(not(feature = "std"))]
+use crate::clock::MissingClock;
+#[cfg(feature = "std")]
+pub use crate::clock::SystemClock;
+pub use crate::clock::{Clock, Instant};
+pub use crate::encoding::DecodeError;
+#[cfg(not(feature = "std"))]
+use crate::math::FloatExt;
+#[cfg(feature = "metrics")]
+pub use crate::metrics::MetricsRecorder;
+#[cfg(feature = "std")]
+pub use crate::sync::SyncHistogram;
+pub use crate::tumbling::TumblingWindowHistogram;
+
+mod clock;
+#[cfg(feature = "debug")]
+mod debug;
+mod encoding;
+#[cfg(feature = "hdrhistogram")]
+mod hdr;
+#[cfg(not(feature = "std"))]
+mod math;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(feature = "statsd")]
+mod statsd;
+#[cfg(feature = "std")]
+mod sync;
+mod tumbling;
 
 const RESCALE_THRESHOLD: Duration = Duration::from_secs(60 * 60);
+const LOGICAL_TICK: Duration = Duration::from_secs(1);
+// An upper bound on the reciprocal of the random numbers used to compute priorities.
+const PRIORITY_HEADROOM: f64 = (1u64 << 54) as f64;
 
-#[derive(Debug)]
-struct WeightedSample {
-    value: i64,
+// Weights are summed into snapshots and divided by a random number in (0, 1) to compute
+// priorities, so they must leave enough headroom for neither to overflow.
+fn max_weight(size: usize) -> f64 {
+    f64::MAX / PRIORITY_HEADROOM / size as f64
+}
+// The maximum number of buckets returned by Snapshot::to_log_buckets.
+const MAX_LOG_BUCKETS: usize = 1024;
+// The effective number of values required beyond a quantile to consider it reliable.
+const MIN_TAIL_SAMPLES: f64 = 5.;
+
+/// A type of value which can be recorded in an [`ExponentialDecayHistogram`].
+///
+/// Statistics of an empty [`Snapshot`] which would otherwise be undefined are reported as the
+/// type's default value (i.e. 0 for integers).
+pub trait Value: Copy + Ord + Default {
+    /// Converts the value to an `f64` for use in statistics such as the mean.
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_value {
+    ($($t:ty),*) => {
+        $(
+            impl Value for $t {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+impl_value!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Floating point values are recorded as [`NotNan<f64>`]s so they can be sorted. See
+/// [`ExponentialDecayHistogramF64`].
+impl Value for NotNan<f64> {
+    fn to_f64(self) -> f64 {
+        self.into_inner()
+    }
+}
+
+/// A histogram of `f64` values.
+///
+/// Values are stored as [`NotNan<f64>`]s, which dereference to `f64`, so statistics like
+/// [`Snapshot::value`] can be read as `*snapshot.value(0.5)`. Values can be recorded directly as
+/// `f64`s with [`ExponentialDecayHistogram::update_f64`].
+///
+/// ```
+/// use exponential_decay_histogram::ExponentialDecayHistogramF64;
+///
+/// let mut histogram = ExponentialDecayHistogramF64::new();
+/// histogram.update_f64(0.25);
+/// histogram.update_f64(0.75);
+///
+/// let snapshot = histogram.snapshot();
+/// assert_eq!(*snapshot.min(), 0.25);
+/// assert_eq!(*snapshot.max(), 0.75);
+/// ```
+pub type ExponentialDecayHistogramF64 = ExponentialDecayHistogram<NotNan<f64>>;
+
+#[derive(Debug, Clone)]
+struct WeightedSample<T> {
+    priority: NotNan<f64>,
+    value: T,
     weight: f64,
 }
 
+// A predicate deciding which values are recorded, wrapped so the histogram can still derive `Debug`
+// and `Clone`.
+#[derive(Clone)]
+struct Filter<T>(Arc<dyn Fn(T) -> bool + Sync + Send>);
+
+impl<T> fmt::Debug for Filter<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("Filter")
+    }
+}
+
+// Samples are ordered by their priority in reverse, so the root of the reservoir's max-heap is the
+// lowest priority sample. Distinct samples can have equal priorities - the heap simply stores both,
+// so a collision never causes a sample to be replaced.
+impl<T> PartialEq for WeightedSample<T> {
+    fn eq(&self, other: &WeightedSample<T>) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl<T> Eq for WeightedSample<T> {}
+
+impl<T> PartialOrd for WeightedSample<T> {
+    fn partial_cmp(&self, other: &WeightedSample<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for WeightedSample<T> {
+    fn cmp(&self, other: &WeightedSample<T>) -> Ordering {
+        other.priority.cmp(&self.priority)
+    }
+}
+
 /// A histogram which exponentially weights in favor of recent values.
 ///
+/// The histogram records values of any type implementing [`Value`], defaulting to `i64`. It
+/// samples values with a random number generator of type `R`, which defaults to `SmallRng` - see
+/// [`Builder::build_with_rng`] to use a different one.
+///
+/// Cloning a histogram copies its random number generator state along with its values, so the
+/// clone will make the same sampling decisions as the original if both are updated identically.
+///
 /// See the crate level documentation for more details.
-#[derive(Debug)]
-pub struct ExponentialDecayHistogram {
-    values: BTreeMap<NotNan<f64>, WeightedSample>,
+#[derive(Debug, Clone)]
+pub struct ExponentialDecayHistogram<T = i64, R = SmallRng> {
+    values: BinaryHeap<WeightedSample<T>>,
     alpha: f64,
     size: usize,
     count: u64,
+    evicted: u64,
+    epoch: Instant,
     start_time: Instant,
     next_scale_time: Instant,
-    rng: SmallRng,
+    rescale_threshold: Duration,
+    clock: Arc<dyn Clock>,
+    logical_time: Option<Instant>,
+    filter: Option<Filter<T>>,
+    rng: R,
 }
 
-impl Default for ExponentialDecayHistogram {
-    fn default() -> ExponentialDecayHistogram {
+impl<T> Default for ExponentialDecayHistogram<T>
+where
+    T: Value,
+{
+    fn default() -> ExponentialDecayHistogram<T> {
         ExponentialDecayHistogram::new()
     }
 }
 
-impl ExponentialDecayHistogram {
+/// Inserts each value into the histogram at the current time.
+impl<T, R> Extend<T> for ExponentialDecayHistogram<T, R>
+where
+    T: Value,
+    R: Rng,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for value in iter {
+            self.update(value);
+        }
+    }
+}
+
+/// Creates a histogram with a default configuration and inserts each value into it at the current
+/// time.
+impl<T> iter::FromIterator<T> for ExponentialDecayHistogram<T>
+where
+    T: Value,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut histogram = ExponentialDecayHistogram::new();
+        histogram.extend(iter);
+        histogram
+    }
+}
+
+/// Creates a histogram with a default configuration and inserts each value into it at the current
+/// time.
+impl<T> From<&[T]> for ExponentialDecayHistogram<T>
+where
+    T: Value,
+{
+    fn from(values: &[T]) -> Self {
+        values.iter().copied().collect()
+    }
+}
+
+/// Creates a histogram with a default configuration and inserts each value into it at the current
+/// time.
+impl<T> From<Vec<T>> for ExponentialDecayHistogram<T>
+where
+    T: Value,
+{
+    fn from(values: Vec<T>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<T> ExponentialDecayHistogram<T>
+where
+    T: Value,
+{
     /// Returns a new histogram with a default configuration.
     pub fn new() -> Self {
         Self::builder().build()
     }
 
     /// Returns a new builder to create a histogram with a custom configuration.
-    pub fn builder() -> Builder {
+    pub fn builder() -> Builder<T> {
         Builder {
-            now: Instant::now(),
+            now: None,
+            #[cfg(feature = "std")]
+            clock: Arc::new(SystemClock),
+            #[cfg(not(feature = "std"))]
+            clock: Arc::new(MissingClock),
             size: 1028,
             alpha: 0.015,
+            rescale_threshold: RESCALE_THRESHOLD,
+            start_elapsed: Duration::from_secs(0),
+            seed: None,
+            logical_clock: false,
+            filter: None,
+            samples: vec![],
         }
     }
 
@@ -111,459 +352,5026 @@ impl ExponentialDecayHistogram {
     pub fn with_size_and_alpha(size: usize, alpha: f64) -> Self {
         Self::builder().size(size).alpha(alpha).build()
     }
+}
 
-    /// Inserts a value into the histogram at the current time.
-    pub fn update(&mut self, value: i64) {
-        self.update_at(Instant::now(), value);
+impl<T, R> ExponentialDecayHistogram<T, R>
+where
+    T: Value,
+    R: Rng,
+{
+    /// Returns the decay rate of the histogram.
+    pub fn alpha(&self) -> f64 {
+        self.alpha
     }
 
-    /// Inserts a value into the histogram at the specified time.
+    /// Sets the decay rate of the histogram at the current time.
+    ///
+    /// See [`ExponentialDecayHistogram::set_alpha_at`] for details.
     ///
     /// # Panics
     ///
-    /// May panic if values are inserted at non-monotonically increasing times.
-    pub fn update_at(&mut self, time: Instant, value: i64) {
-        self.rescale_if_needed(time);
-        self.count += 1;
-
-        let item_weight = self.weight(time);
-        let sample = WeightedSample {
-            value,
-            weight: item_weight,
-        };
-        // Open01 since we don't want to divide by 0
-        let priority = item_weight / self.rng.sample::<f64, _>(&Open01);
-        let priority = NotNan::new(priority).unwrap();
-
-        if self.values.len() < self.size {
-            self.values.insert(priority, sample);
-        } else {
-            let first = *self.values.keys().next().unwrap();
-            if first < priority && self.values.insert(priority, sample).is_none() {
-                self.values.remove(&first).unwrap();
-            }
-        }
+    /// Panics if `alpha` is negative, NaN, or infinite.
+    pub fn set_alpha(&mut self, alpha: f64) {
+        let now = self.now();
+        self.set_alpha_at(now, alpha);
     }
 
-    /// Takes a snapshot of the current state of the histogram.
-    pub fn snapshot(&self) -> Snapshot {
-        let mut entries = self
-            .values
-            .values()
-            .map(|s| SnapshotEntry {
-                value: s.value,
-                norm_weight: s.weight,
-                quantile: NotNan::new(0.).unwrap(),
-            })
-            .collect::<Vec<_>>();
-
-        entries.sort_by_key(|e| e.value);
-
-        let sum_weight = entries.iter().map(|e| e.norm_weight).sum::<f64>();
-        for entry in &mut entries {
-            entry.norm_weight /= sum_weight;
-        }
-
-        entries.iter_mut().fold(NotNan::new(0.).unwrap(), |acc, e| {
-            e.quantile = acc;
-            acc + e.norm_weight
-        });
-
-        Snapshot {
-            entries,
-            count: self.count,
-        }
-    }
+    /// Sets the decay rate of the histogram at the specified time.
+    ///
+    /// The histogram is first rescaled to `time`, so the weights of its existing values reflect
+    /// the decay they experienced under the old alpha up to that point. From then on, all values -
+    /// both existing and new - decay at the new rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` is negative, NaN, or infinite. May panic if `time` is before the
+    /// histogram's current decay landmark.
+    pub fn set_alpha_at(&mut self, time: Instant, alpha: f64) {
+        assert!(
+            alpha >= 0. && alpha.is_finite(),
+            "alpha must be non-negative and finite"
+        );
 
-    fn weight(&self, time: Instant) -> f64 {
-        (self.alpha * (time - self.start_time).as_secs() as f64).exp()
+        self.rescale(time);
+        self.alpha = alpha;
     }
 
-    fn rescale_if_needed(&mut self, now: Instant) {
-        if now >= self.next_scale_time {
-            self.rescale(now);
-        }
+    /// Returns the maximum number of values stored in the histogram.
+    pub fn size(&self) -> usize {
+        self.size
     }
 
-    fn rescale(&mut self, now: Instant) {
-        self.next_scale_time = now + RESCALE_THRESHOLD;
-        let old_start_time = self.start_time;
-        self.start_time = now;
-        let scaling_factor = (-self.alpha * (now - old_start_time).as_secs() as f64).exp();
-
-        self.values = self
-            .values
-            .iter()
-            .map(|(&k, v)| {
-                (
-                    k * scaling_factor,
-                    WeightedSample {
-                        value: v.value,
-                        weight: v.weight * scaling_factor,
-                    },
-                )
-            })
-            .collect();
+    /// Returns the mean of the values in the histogram, or 0 if it is empty.
+    ///
+    /// This is the same statistic as [`Snapshot::mean`], but is computed directly from the
+    /// histogram's values without allocating or sorting a snapshot. Since the values are summed in
+    /// a different order, the two may differ by rounding error.
+    pub fn mean_now(&self) -> f64 {
+        weighted_mean(self.values.iter().map(|s| (s.value, s.weight)))
     }
-}
-
-/// A builder type for [`ExponentialDecayHistogram`] objects.
-pub struct Builder {
-    now: Instant,
-    size: usize,
-    alpha: f64,
-}
 
-impl Builder {
-    /// Sets the construction time of the histogram.
+    /// Returns the number of values which have been discarded from the histogram.
     ///
-    /// Defaults to the system time when the [`Builder`] was constructed.
-    pub fn at(&mut self, now: Instant) -> &mut Self {
-        self.now = now;
-        self
+    /// Once the histogram is full, each insert discards either the lowest priority value or the
+    /// new value. Values are also discarded when the histogram shrinks, or when it rescales after a
+    /// long period of inactivity. A high rate of evictions relative to the histogram's size
+    /// indicates that it retains a small fraction of the values written to it. Like the count,
+    /// this is reset when the histogram is cleared and saturates at `u64::MAX`.
+    pub fn evicted_count(&self) -> u64 {
+        self.evicted
     }
 
-    /// Sets the size of the histogram.
-    ///
-    /// A larger size will provide a more accurate histogram, but with a higher memory overhead.
+    /// Sets the maximum number of values stored in the histogram.
     ///
-    /// Defaults to 1028, which offers a 99.9% confidence level with a 5% margin of error.
+    /// If the histogram currently holds more than `size` values, the lowest priority values are
+    /// discarded until it holds `size`. Growing the histogram doesn't affect its existing values,
+    /// but allows more to be retained by future inserts.
     ///
     /// # Panics
     ///
     /// Panics if `size` is 0.
-    pub fn size(&mut self, size: usize) -> &mut Self {
+    pub fn set_size(&mut self, size: usize) {
         assert!(size > 0);
 
         self.size = size;
-        self
+        while self.values.len() > size {
+            self.values.pop();
+            self.evicted = self.evicted.saturating_add(1);
+        }
     }
 
-    /// Sets the decay rate of the histogram.
+    /// Allocates space for the histogram to hold [`ExponentialDecayHistogram::size`] values.
     ///
-    /// A larger value biases the histogram towards newer values.
+    /// The histogram's buffer normally grows as values are inserted, reallocating several times
+    /// before it is full. Reserving it up front avoids that churn, which can be worthwhile when
+    /// creating many histograms which are expected to fill up.
+    pub fn reserve(&mut self) {
+        let additional = self.size.saturating_sub(self.values.len());
+        self.values.reserve_exact(additional);
+    }
+
+    /// Returns the number of values currently retained by the histogram.
     ///
-    /// Defaults to 0.015, which heavily biases towards the last 5 minutes of values.
-    pub fn alpha(&mut self, alpha: f64) -> &mut Self {
-        self.alpha = alpha;
-        self
+    /// This grows as values are inserted until it reaches [`ExponentialDecayHistogram::size`]. It
+    /// is the number of values a snapshot of the histogram would contain (unless all of their
+    /// weights have decayed to 0), so the cost of taking a snapshot is proportional to it.
+    pub fn reservoir_len(&self) -> usize {
+        self.values.len()
     }
 
-    /// Creates a new [`ExponentialDecayHistogram`].
-    pub fn build(&self) -> ExponentialDecayHistogram {
-        ExponentialDecayHistogram {
-            values: BTreeMap::new(),
-            alpha: self.alpha,
-            size: self.size,
-            count: 0,
-            start_time: self.now,
-            // we store this explicitly because it's ~10% faster than doing the math on demand
-            next_scale_time: self.now + RESCALE_THRESHOLD,
-            // using a SmallRng is ~10% faster than using thread_rng()
-            rng: SmallRng::from_rng(&mut rand::rng()),
-        }
+    /// Returns the number of values which have been written to the histogram.
+    ///
+    /// The count saturates at `u64::MAX` rather than wrapping.
+    pub fn count(&self) -> u64 {
+        self.count
     }
-}
 
-struct SnapshotEntry {
-    value: i64,
-    norm_weight: f64,
-    quantile: NotNan<f64>,
-}
+    /// Returns an estimate of the number of bytes of memory used by the histogram.
+    ///
+    /// This is `size_of::<ExponentialDecayHistogram<T>>() + capacity * sample_size`, where
+    /// `capacity` is the capacity of the heap-allocated buffer storing the histogram's values and
+    /// `sample_size` is the size of a single stored value along with its weight and priority. The
+    /// buffer grows as values are inserted until it holds [`ExponentialDecayHistogram::size`]
+    /// values, or can be allocated up front with [`ExponentialDecayHistogram::reserve`]. Allocator
+    /// overhead and the histogram's clock are not included.
+    pub fn estimated_memory_bytes(&self) -> usize {
+        mem::size_of::<Self>() + self.values.capacity() * mem::size_of::<WeightedSample<T>>()
+    }
 
-/// A snapshot of the state of an `ExponentialDecayHistogram` at some point in time.
-pub struct Snapshot {
-    entries: Vec<SnapshotEntry>,
-    count: u64,
-}
+    /// Returns the histogram's epoch.
+    ///
+    /// This is the histogram's construction time, and unlike its [decay landmark], it never
+    /// changes. Times can be specified as offsets from it with
+    /// [`ExponentialDecayHistogram::update_elapsed`] and [`ExponentialDecayHistogram::instant_at`].
+    ///
+    /// [decay landmark]: ExponentialDecayHistogram::start_time
+    pub fn epoch(&self) -> Instant {
+        self.epoch
+    }
 
-impl Snapshot {
-    /// Returns the value at a specified quantile in the snapshot, or 0 if it is
-    /// empty.
+    /// Returns the time a specified amount of time after the histogram's epoch.
     ///
-    /// For example, `snapshot.value(0.5)` returns the median value of the
-    /// snapshot.
+    /// This can be used to drive the `*_at` methods with plain offsets like "100 seconds in"
+    /// rather than `Instant`s, which can't be constructed arbitrarily. Offsets too large to be
+    /// represented are clamped.
+    pub fn instant_at(&self, elapsed: Duration) -> Instant {
+        saturating_add(self.epoch, elapsed)
+    }
+
+    /// Inserts a value into the histogram at a specified amount of time after its epoch.
+    ///
+    /// This is equivalent to `histogram.update_at(histogram.instant_at(elapsed), value)`, so a
+    /// histogram can be driven entirely by a tick counter in tests and simulations:
+    ///
+    /// ```
+    /// use exponential_decay_histogram::ExponentialDecayHistogram;
+    /// use std::time::Duration;
+    ///
+    /// let mut histogram = ExponentialDecayHistogram::new();
+    /// for tick in 0..100 {
+    ///     histogram.update_elapsed(Duration::from_secs(tick), tick as i64);
+    /// }
+    /// ```
     ///
     /// # Panics
     ///
-    /// Panics if `quantile` is not between 0 and 1 (inclusive).
-    pub fn value(&self, quantile: f64) -> i64 {
-        assert!(quantile >= 0. && quantile <= 1.);
+    /// May panic if values are inserted at non-monotonically increasing times.
+    pub fn update_elapsed(&mut self, elapsed: Duration, value: T) {
+        let time = self.instant_at(elapsed);
+        self.update_at(time, value);
+    }
 
-        if self.entries.is_empty() {
-            return 0;
-        }
+    /// Returns the histogram's decay landmark.
+    ///
+    /// The weights of values are computed relative to this time. It is initially the histogram's
+    /// construction time, and is moved forward each time the histogram rescales its weights or is
+    /// cleared, so it is also the time of the last rescale.
+    pub fn start_time(&self) -> Instant {
+        self.start_time
+    }
 
-        let quantile = NotNan::new(quantile).unwrap();
-        let idx = match self.entries.binary_search_by(|e| e.quantile.cmp(&quantile)) {
-            Ok(idx) => idx,
-            Err(idx) if idx >= self.entries.len() => self.entries.len() - 1,
-            Err(idx) => idx,
-        };
+    /// Returns the time at or after which the next insert will rescale the histogram's weights.
+    pub fn next_rescale_time(&self) -> Instant {
+        self.next_scale_time
+    }
 
-        self.entries[idx].value
+    /// Returns the amount of time remaining from `now` until the next insert will rescale the
+    /// histogram's weights, or zero if that time has already passed.
+    pub fn time_until_rescale(&self, now: Instant) -> Duration {
+        self.next_scale_time.saturating_duration_since(now)
     }
 
-    /// Returns the largest value in the snapshot, or 0 if it is empty.
-    pub fn max(&self) -> i64 {
-        self.entries.last().map_or(0, |e| e.value)
+    /// Inserts a value into the histogram at the current time.
+    pub fn update(&mut self, value: T) {
+        let now = self.tick();
+        self.update_at(now, value);
     }
 
-    /// Returns the smallest value in the snapshot, or 0 if it is empty.
-    pub fn min(&self) -> i64 {
-        self.entries.first().map_or(0, |e| e.value)
+    /// Inserts a value into the histogram at the specified time.
+    ///
+    /// Values rejected by the histogram's [filter](Builder::filter) are ignored.
+    ///
+    /// # Panics
+    ///
+    /// May panic if values are inserted at non-monotonically increasing times.
+    pub fn update_at(&mut self, time: Instant, value: T) {
+        self.update_many_at(time, value, 1);
     }
 
-    /// Returns the mean of the values in the snapshot, or 0 if it is empty.
-    pub fn mean(&self) -> f64 {
-        self.entries
-            .iter()
-            .map(|e| e.value as f64 * e.norm_weight)
-            .sum::<f64>()
+    /// Inserts a value into the histogram multiple times at the current time.
+    ///
+    /// See [`ExponentialDecayHistogram::update_many_at`] for details.
+    pub fn update_many(&mut self, value: T, count: u64) {
+        let now = self.tick();
+        self.update_many_at(now, value, count);
     }
 
-    /// Returns the standard deviation of the values in the snapshot, or 0 if it
-    /// is empty.
-    pub fn stddev(&self) -> f64 {
-        if self.entries.len() <= 1 {
-            return 0.;
+    /// Inserts a value into the histogram multiple times at the specified time.
+    ///
+    /// The histogram's count is incremented by `count`, but the value is stored as a single sample
+    /// with `count` times the weight of an individual insert. It therefore takes up at most one
+    /// slot in the histogram, while contributing the same total weight to its statistics as
+    /// `count` individual inserts would if they were all retained.
+    ///
+    /// # Panics
+    ///
+    /// May panic if values are inserted at non-monotonically increasing times.
+    pub fn update_many_at(&mut self, time: Instant, value: T, count: u64) {
+        if count == 0 || !self.accepts(value) {
+            return;
         }
 
-        let mean = self.mean();
-        let variance = self
-            .entries
-            .iter()
-            .map(|e| {
-                let diff = e.value as f64 - mean;
-                e.norm_weight * diff * diff
-            })
-            .sum::<f64>();
-
-        variance.sqrt()
+        self.rescale_if_needed(time);
+        self.count = self.count.saturating_add(count);
+        self.insert_at(time, value, count as f64);
     }
 
-    /// Returns the number of values which have been written to the histogram at
-    /// the time of the snapshot.
-    pub fn count(&self) -> u64 {
-        self.count
+    /// Inserts a value with an explicit weight into the histogram at the current time.
+    ///
+    /// See [`ExponentialDecayHistogram::update_weighted_at`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is not positive and finite, or is too large to be stored.
+    pub fn update_weighted(&mut self, value: T, weight: f64) {
+        let now = self.tick();
+        self.update_weighted_at(now, value, weight);
     }
 
-    /// Returns an iterator over the distinct values in the snapshot along with their weights.
-    pub fn values<'a>(&'a self) -> Values<'a> {
-        Values {
-            it: self.entries.iter().peekable(),
+    /// Inserts a value with an explicit weight into the histogram at the specified time.
+    ///
+    /// The value's decay weight is multiplied by `weight`, so it contributes to the histogram's
+    /// statistics (and is retained) as if it were `weight` values. The histogram's count is
+    /// incremented by 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is not positive and finite, or is too large to be stored - roughly
+    /// `1e291 / size`. May panic if values are inserted at non-monotonically increasing times.
+    pub fn update_weighted_at(&mut self, time: Instant, value: T, weight: f64) {
+        assert!(
+            weight > 0. && weight.is_finite(),
+            "weight must be positive and finite"
+        );
+
+        if !self.accepts(value) {
+            return;
         }
+
+        self.rescale_if_needed(time);
+        self.count = self.count.saturating_add(1);
+        self.insert_at(time, value, weight);
     }
-}
 
-/// An iterator over the distinct values in a snapshot along with their weights.
-pub struct Values<'a> {
-    it: iter::Peekable<slice::Iter<'a, SnapshotEntry>>,
-}
+    /// Inserts a value into the histogram at the specified time, returning an error if the time is
+    /// before the histogram's decay landmark.
+    ///
+    /// The landmark is the histogram's construction time, and moves forward each time the
+    /// histogram rescales its weights.
+    pub fn try_update_at(&mut self, time: Instant, value: T) -> Result<(), UpdateError> {
+        if time < self.start_time {
+            return Err(UpdateError::NonMonotonicTime);
+        }
 
-impl<'a> Iterator for Values<'a> {
-    type Item = (i64, f64);
+        self.update_at(time, value);
+        Ok(())
+    }
 
-    fn next(&mut self) -> Option<(i64, f64)> {
-        let (value, mut weight) = match self.it.next() {
-            Some(v) => (v.value, v.norm_weight),
-            None => return None,
-        };
+    /// Merges the values of another histogram into this one.
+    ///
+    /// If `other`'s decay landmark is later than this histogram's, this histogram is first
+    /// rescaled to it. The weights of `other`'s values are then adjusted to be relative to this
+    /// histogram's landmark, and only the highest priority values are retained if the combined
+    /// number exceeds this histogram's size. The resulting count is the sum of both histograms'
+    /// counts.
+    ///
+    /// The two histograms are assumed to have been configured with the same alpha; this
+    /// histogram's alpha is used when adjusting `other`'s weights.
+    pub fn merge<R2>(&mut self, other: &ExponentialDecayHistogram<T, R2>) {
+        // Scaling weights up to a later landmark can overflow, so both histograms are brought to
+        // the later of the two, and other's weights are only ever scaled down.
+        if other.start_time > self.start_time {
+            self.rescale(other.start_time);
+        }
+        let scaling_factor =
+            (-self.alpha * (self.start_time - other.start_time).as_secs_f64()).exp();
 
-        loop {
-            match self.it.peek() {
-                Some(v) if v.value == value => weight += v.norm_weight,
-                _ => break,
-            }
-            self.it.next();
+        for sample in &other.values {
+            self.insert(WeightedSample {
+                priority: sample.priority * scaling_factor,
+                value: sample.value,
+                weight: sample.weight * scaling_factor,
+            });
         }
 
-        Some((value, weight))
+        self.count = self.count.saturating_add(other.count);
+        self.evicted = self.evicted.saturating_add(other.evicted);
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use std::ops::Range;
+    /// Merges a collection of histograms into one, or returns `None` if it is empty.
+    ///
+    /// The first histogram is used as the base of the result, so its configuration - including
+    /// its alpha and size - is retained, and the values of the others are merged into it as if by
+    /// [`ExponentialDecayHistogram::merge`]. The result's decay landmark is the latest of all of
+    /// the histograms' landmarks, and each histogram's weights are scaled down to it once. Only
+    /// the highest priority values are retained if the combined number exceeds the first
+    /// histogram's size.
+    pub fn merge_all<I>(histograms: I) -> Option<ExponentialDecayHistogram<T, R>>
+    where
+        I: IntoIterator<Item = ExponentialDecayHistogram<T, R>>,
+    {
+        let histograms = histograms.into_iter().collect::<Vec<_>>();
+        let landmark = histograms.iter().map(|h| h.start_time).max()?;
 
-    #[test]
-    fn a_histogram_of_100_out_of_1000_elements() {
-        let mut histogram = ExponentialDecayHistogram::builder()
-            .size(100)
-            .alpha(0.99)
-            .build();
-        for i in 0..1000 {
-            histogram.update(i);
+        let mut histograms = histograms.into_iter();
+        let mut merged = histograms.next()?;
+        if landmark > merged.start_time {
+            merged.rescale(landmark);
+        }
+        for histogram in histograms {
+            merged.merge(&histogram);
         }
 
-        assert_eq!(histogram.values.len(), 100);
-
-        let snapshot = histogram.snapshot();
+        Some(merged)
+    }
 
-        assert_eq!(snapshot.entries.len(), 100);
+    /// Removes all values from the histogram, resetting it to the current time.
+    ///
+    /// The histogram's configuration is preserved.
+    pub fn clear(&mut self) {
+        self.clear_at(self.now());
+    }
 
-        assert_all_values_between(snapshot, 0..1000);
+    /// Removes all values from the histogram, resetting it to the specified time.
+    ///
+    /// The histogram's configuration is preserved.
+    pub fn clear_at(&mut self, time: Instant) {
+        self.values.clear();
+        self.count = 0;
+        self.evicted = 0;
+        self.start_time = time;
+        self.next_scale_time = saturating_add(time, self.rescale_threshold);
+    }
+
+    /// Rescales the histogram's weights at the current time.
+    ///
+    /// See [`ExponentialDecayHistogram::rescale_at`] for details.
+    pub fn rescale_now(&mut self) {
+        let now = self.now();
+        self.rescale(now);
+    }
+
+    /// Rescales the histogram's weights at the specified time.
+    ///
+    /// The histogram normally rescales automatically once its rescale threshold has elapsed. This
+    /// moves the decay landmark to `time` and resets the rescale timer. The relative weights of
+    /// the values in the histogram, and therefore its statistics, are unaffected, unless `time` is
+    /// far enough past the landmark that every weight underflows to 0. Snapshots are then empty
+    /// until the next insert.
+    ///
+    /// # Panics
+    ///
+    /// May panic if `time` is before the histogram's current decay landmark.
+    pub fn rescale_at(&mut self, time: Instant) {
+        self.rescale(time);
+    }
+
+    /// Takes a snapshot of the current state of the histogram and then clears it.
+    ///
+    /// This is equivalent to calling [`ExponentialDecayHistogram::snapshot`] followed by
+    /// [`ExponentialDecayHistogram::clear`], so the histogram's decay landmark is reset to the
+    /// current time. Since it takes `&mut self`, no values can be inserted between the two steps.
+    pub fn snapshot_and_reset(&mut self) -> Snapshot<T> {
+        let snapshot = self.snapshot();
+        self.clear();
+        snapshot
+    }
+
+    /// Takes a snapshot of the current state of the histogram.
+    ///
+    /// If the weights of all of the histogram's values have decayed to 0, which can happen if it
+    /// is rescaled long after its last insert, the snapshot contains no values but still reports
+    /// the histogram's count.
+    pub fn snapshot(&self) -> Snapshot<T> {
+        let mut snapshot = Snapshot::default();
+        self.snapshot_into(&mut snapshot);
+        snapshot
+    }
+
+    /// Takes a snapshot of the current state of the histogram, storing it in an existing snapshot.
+    ///
+    /// The snapshot's previous contents are overwritten, but its allocation is reused. Repeatedly
+    /// refreshing the same snapshot avoids allocating on each call:
+    ///
+    /// ```
+    /// use exponential_decay_histogram::{ExponentialDecayHistogram, Snapshot};
+    ///
+    /// let mut histogram = ExponentialDecayHistogram::new();
+    /// let mut snapshot = Snapshot::default();
+    ///
+    /// for i in 0..10 {
+    ///     histogram.update(i);
+    ///     histogram.snapshot_into(&mut snapshot);
+    ///     println!("median: {}", snapshot.value(0.5));
+    /// }
+    /// ```
+    pub fn snapshot_into(&self, snapshot: &mut Snapshot<T>) {
+        let entries = &mut snapshot.entries;
+        entries.clear();
+        entries.extend(self.values.iter().map(|s| SnapshotEntry {
+            value: s.value,
+            norm_weight: s.weight,
+            quantile: NotNan::new(0.).unwrap(),
+        }));
+
+        entries.sort_by_key(|e| e.value);
+
+        snapshot.count = self.count;
+
+        // If the histogram is rescaled long after its last insert, the weights of all of its
+        // values can underflow to 0, leaving no distribution to report.
+        let sum_weight = entries.iter().map(|e| e.norm_weight).sum::<f64>();
+        if sum_weight == 0. {
+            entries.clear();
+            snapshot.mean = 0.;
+            snapshot.total_weight = 0.;
+            return;
+        }
+
+        for entry in entries.iter_mut() {
+            entry.norm_weight /= sum_weight;
+        }
+
+        entries.iter_mut().fold(NotNan::new(0.).unwrap(), |acc, e| {
+            e.quantile = acc;
+            acc + e.norm_weight
+        });
+
+        snapshot.mean = weighted_mean(entries.iter().map(|e| (e.value, e.norm_weight)));
+        snapshot.total_weight = sum_weight;
+    }
+
+    fn now(&self) -> Instant {
+        match self.logical_time {
+            Some(time) => time,
+            None => self.clock.now(),
+        }
+    }
+
+    // Returns the time at which to insert a new value, advancing the logical clock if it's in use.
+    fn tick(&mut self) -> Instant {
+        match &mut self.logical_time {
+            Some(time) => {
+                *time = saturating_add(*time, LOGICAL_TICK);
+                *time
+            }
+            None => self.clock.now(),
+        }
+    }
+
+    fn accepts(&self, value: T) -> bool {
+        match &self.filter {
+            Some(filter) => (filter.0)(value),
+            None => true,
+        }
+    }
+
+    fn insert_at(&mut self, time: Instant, value: T, weight: f64) {
+        // Weights can grow too large with rescaling disabled or an explicit weight after a long
+        // gap, in which case the histogram is rescaled to `time` so the decay factor is 1.
+        let max_weight = max_weight(self.size);
+        assert!(
+            weight <= max_weight,
+            "weight is too large to be stored in the histogram"
+        );
+
+        let mut item_weight = self.weight(time) * weight;
+        if item_weight > max_weight {
+            self.rescale(time);
+            item_weight = self.weight(time) * weight;
+        }
+
+        // Open01 since we don't want to divide by 0
+        let priority = item_weight / self.rng.sample::<f64, _>(&Open01);
+        let sample = WeightedSample {
+            priority: NotNan::new(priority).unwrap(),
+            value,
+            weight: item_weight,
+        };
+
+        self.insert(sample);
+    }
+
+    fn insert(&mut self, sample: WeightedSample<T>) {
+        if self.values.len() < self.size {
+            self.values.push(sample);
+        } else {
+            // either the lowest priority sample or the new one is dropped
+            self.evicted = self.evicted.saturating_add(1);
+            let mut first = self.values.peek_mut().unwrap();
+            if first.priority < sample.priority {
+                *first = sample;
+            }
+        }
+    }
+
+    fn weight(&self, time: Instant) -> f64 {
+        (self.alpha * (time - self.start_time).as_secs_f64()).exp()
+    }
+
+    fn rescale_if_needed(&mut self, now: Instant) {
+        if now >= self.next_scale_time {
+            self.rescale(now);
+        }
+    }
+
+    fn rescale(&mut self, now: Instant) {
+        self.next_scale_time = saturating_add(now, self.rescale_threshold);
+        let old_start_time = self.start_time;
+        self.start_time = now;
+        let scaling_factor = (-self.alpha * (now - old_start_time).as_secs_f64()).exp();
+
+        // Scaling every priority by the same factor preserves their relative order, so the heap's
+        // buffer can be updated in place without re-sorting it.
+        let mut values = mem::take(&mut self.values).into_vec();
+        let mut underflowed = false;
+        for sample in &mut values {
+            sample.priority *= scaling_factor;
+            sample.weight *= scaling_factor;
+            underflowed |= *sample.priority == 0.;
+        }
+
+        // After a long period of inactivity, priorities can underflow to 0. Only one such sample
+        // is kept so stale samples don't linger in the reservoir. Samples whose priorities merely
+        // collide are unaffected.
+        if underflowed {
+            let len = values.len();
+            let mut kept_zero = false;
+            values.retain(|s| *s.priority != 0. || !mem::replace(&mut kept_zero, true));
+            self.evicted = self.evicted.saturating_add((len - values.len()) as u64);
+        }
+
+        self.values = BinaryHeap::from(values);
+    }
+}
+
+/// Methods for recording `Duration`s as a number of nanoseconds.
+///
+/// Durations longer than `i64::MAX` nanoseconds (roughly 292 years) are clamped to that value.
+impl<R> ExponentialDecayHistogram<i64, R>
+where
+    R: Rng,
+{
+    /// Inserts a duration into the histogram at the current time.
+    pub fn update_duration(&mut self, value: Duration) {
+        self.update(duration_to_nanos(value));
+    }
+
+    /// Inserts a duration into the histogram at the specified time.
+    ///
+    /// # Panics
+    ///
+    /// May panic if values are inserted at non-monotonically increasing times.
+    pub fn update_duration_at(&mut self, time: Instant, value: Duration) {
+        self.update_at(time, duration_to_nanos(value));
+    }
+}
+
+/// Methods for recording `f64` values.
+impl<R> ExponentialDecayHistogram<NotNan<f64>, R>
+where
+    R: Rng,
+{
+    /// Inserts a value into the histogram at the current time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is NaN.
+    pub fn update_f64(&mut self, value: f64) {
+        self.update(not_nan(value));
+    }
+
+    /// Inserts a value into the histogram at the specified time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is NaN. May panic if values are inserted at non-monotonically increasing
+    /// times.
+    pub fn update_f64_at(&mut self, time: Instant, value: f64) {
+        self.update_at(time, not_nan(value));
+    }
+}
+
+fn not_nan(value: f64) -> NotNan<f64> {
+    NotNan::new(value).expect("value must not be NaN")
+}
+
+// Acklam's rational approximation of the inverse of the standard normal CDF, with a relative error
+// of less than 1.15e-9.
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.38357751867269e2,
+        -3.066479806614716e1,
+        2.506628277459239e0,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838e0,
+        -2.549732539343734e0,
+        4.374664141464968e0,
+        2.938163982698783e0,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996e0,
+        3.754408661907416e0,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.)
+    };
+
+    if p < P_LOW {
+        tail((-2. * p.ln()).sqrt())
+    } else if p <= 1. - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.)
+    } else {
+        -tail((-2. * (1. - p).ln()).sqrt())
+    }
+}
+
+fn duration_to_nanos(duration: Duration) -> i64 {
+    i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX)
+}
+
+fn nanos_to_duration(nanos: i64) -> Duration {
+    Duration::from_nanos(nanos.max(0) as u64)
+}
+
+// Instant has no maximum value to saturate at, so we instead back off to the largest power-of-two
+// fraction of the duration that can be represented.
+fn saturating_add(time: Instant, mut duration: Duration) -> Instant {
+    loop {
+        if let Some(time) = time.checked_add(duration) {
+            return time;
+        }
+        duration /= 2;
+    }
+}
+
+// The weighted mean of a set of values, or 0 if their total weight is 0. Rounding can push the sum
+// slightly outside of the range of the values, so it's clamped. Otherwise a snapshot of identical
+// values wouldn't have a mean equal to the value, and the spread around it would be nonzero.
+fn weighted_mean<T, I>(values: I) -> f64
+where
+    T: Value,
+    I: Iterator<Item = (T, f64)> + Clone,
+{
+    let sum_weight = compensated_sum(values.clone().map(|(_, weight)| weight));
+    if sum_weight == 0. {
+        return 0.;
+    }
+
+    let (min, max) = values
+        .clone()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            let value = v.0.to_f64();
+            (min.min(value), max.max(value))
+        });
+    let sum = compensated_sum(values.map(|(value, weight)| value.to_f64() * weight));
+
+    (sum / sum_weight).clamp(min, max)
+}
+
+// Neumaier's variant of Kahan summation. Snapshot statistics sum one term per value, and with large
+// values such as byte counts a naive sum loses precision as rounding errors accumulate across
+// terms. This tracks the error separately, so the result is accurate to within a few ulps
+// regardless of the number of terms.
+fn compensated_sum<I>(terms: I) -> f64
+where
+    I: IntoIterator<Item = f64>,
+{
+    let mut sum = 0.;
+    let mut compensation = 0.;
+    for term in terms {
+        let next = sum + term;
+        if sum.abs() >= term.abs() {
+            compensation += (sum - next) + term;
+        } else {
+            compensation += (term - next) + sum;
+        }
+        sum = next;
+    }
+
+    sum + compensation
+}
+
+/// An error returned when inserting a value into an [`ExponentialDecayHistogram`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateError {
+    /// The value's time was before the histogram's decay landmark.
+    NonMonotonicTime,
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::NonMonotonicTime => {
+                fmt.write_str("value inserted before the histogram's decay landmark")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for UpdateError {}
+
+/// A builder type for [`ExponentialDecayHistogram`] objects.
+pub struct Builder<T = i64> {
+    now: Option<Instant>,
+    clock: Arc<dyn Clock>,
+    size: usize,
+    alpha: f64,
+    rescale_threshold: Duration,
+    start_elapsed: Duration,
+    seed: Option<u64>,
+    logical_clock: bool,
+    filter: Option<Filter<T>>,
+    samples: Vec<(T, f64)>,
+}
+
+impl<T> Builder<T>
+where
+    T: Value,
+{
+    /// Returns a builder with the default configuration, suited for general purpose monitoring.
+    ///
+    /// The histogram stores 1028 values, which offers a 99.9% confidence level with a 5% margin of
+    /// error, and has an alpha of 0.015, which heavily biases towards the last 5 minutes of values.
+    /// This is equivalent to [`ExponentialDecayHistogram::builder`].
+    pub fn monitoring_defaults() -> Builder<T> {
+        ExponentialDecayHistogram::builder()
+    }
+
+    /// Returns a builder configured for more accurate quantiles at the cost of memory.
+    ///
+    /// The histogram stores 4112 values - four times the default - which halves the margin of
+    /// error to 2.5% at a 99.9% confidence level, but uses four times as much memory and makes
+    /// snapshots more expensive. The alpha is the default of 0.015.
+    pub fn high_accuracy() -> Builder<T> {
+        let mut builder = Self::monitoring_defaults();
+        builder.size(4112);
+        builder
+    }
+
+    /// Returns a builder configured to track changes in the distribution of values quickly.
+    ///
+    /// The histogram has an alpha of 0.1, which heavily biases towards the last 45 seconds of
+    /// values. It reacts to changes faster than the default, but its statistics are noisier since
+    /// they're dominated by fewer values. The size is the default of 1028.
+    pub fn fast_decay() -> Builder<T> {
+        let mut builder = Self::monitoring_defaults();
+        builder.alpha(0.1);
+        builder
+    }
+
+    /// Sets the construction time of the histogram.
+    ///
+    /// This is also the histogram's [epoch](ExponentialDecayHistogram::epoch). Defaults to the
+    /// clock's current time when the histogram is built.
+    pub fn at(&mut self, now: Instant) -> &mut Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Sets the clock used to determine the current time.
+    ///
+    /// Defaults to [`SystemClock`]. Without the `std` feature there is no default clock, and
+    /// methods which operate at the current time will panic unless one is set.
+    pub fn clock<C>(&mut self, clock: C) -> &mut Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets the size of the histogram.
+    ///
+    /// A larger size will provide a more accurate histogram, but with a higher memory overhead.
+    ///
+    /// Defaults to 1028, which offers a 99.9% confidence level with a 5% margin of error.
+    ///
+    /// Building the histogram fails if `size` is 0.
+    pub fn size(&mut self, size: usize) -> &mut Self {
+        self.size = size;
+        self
+    }
+
+    /// Sets the decay rate of the histogram.
+    ///
+    /// A larger value biases the histogram towards newer values.
+    ///
+    /// Defaults to 0.015, which heavily biases towards the last 5 minutes of values. An alpha of 0
+    /// disables decay entirely, so all values are weighted equally.
+    ///
+    /// Building the histogram fails if `alpha` is negative, NaN, or infinite.
+    pub fn alpha(&mut self, alpha: f64) -> &mut Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the decay rate of the histogram in terms of a half-life.
+    ///
+    /// A value recorded `half_life` ago has half the weight of a value recorded now. The decay
+    /// rate is computed as `alpha = ln(2) / half_life`, with `half_life` in seconds.
+    ///
+    /// This overrides any previous call to [`Builder::alpha`], and vice versa.
+    ///
+    /// Building the histogram fails if `half_life` is 0.
+    pub fn half_life(&mut self, half_life: Duration) -> &mut Self {
+        self.alpha(core::f64::consts::LN_2 / half_life.as_secs_f64())
+    }
+
+    /// Sets the interval at which the histogram rescales its weights.
+    ///
+    /// The weights of values grow exponentially over time relative to the histogram's decay
+    /// landmark, which is periodically reset to keep them within the range of an `f64`. A larger
+    /// alpha may require a shorter interval to avoid overflow.
+    ///
+    /// Defaults to 1 hour.
+    ///
+    /// Building the histogram fails if `threshold` is 0.
+    pub fn rescale_threshold(&mut self, threshold: Duration) -> &mut Self {
+        self.rescale_threshold = threshold;
+        self
+    }
+
+    /// Disables automatic rescaling of the histogram's weights.
+    ///
+    /// This is equivalent to a rescale threshold of `Duration::MAX`, so the histogram's decay
+    /// landmark only moves when it is cleared or explicitly rescaled. It is intended for
+    /// short-lived histograms. The weights of values grow by a factor of `e^alpha` each second, so
+    /// they will overflow an `f64` if the histogram is used for too long - about 13 hours with the
+    /// default alpha. Rather than overflowing, an insert whose weight is too large falls back to
+    /// rescaling the histogram at its time.
+    ///
+    /// This overrides any previous call to [`Builder::rescale_threshold`], and vice versa.
+    pub fn no_rescale(&mut self) -> &mut Self {
+        self.rescale_threshold = Duration::MAX;
+        self
+    }
+
+    /// Sets the amount of time between the histogram's decay landmark and its construction time.
+    ///
+    /// The landmark is normally the construction time, and moves forward each time the histogram
+    /// rescales its weights. Offsetting it resumes the decay phase of a previous histogram, such as
+    /// one restored from a checkpoint, so values are weighted as they would have been had the
+    /// original histogram been used, and the next rescale happens at the same point in the cycle.
+    ///
+    /// Defaults to 0. Building the histogram fails if `elapsed` is greater than the rescale
+    /// threshold, or if the landmark would be before the earliest representable time.
+    pub fn start_elapsed(&mut self, elapsed: Duration) -> &mut Self {
+        self.start_elapsed = elapsed;
+        self
+    }
+
+    /// Sets the seed of the histogram's random number generator.
+    ///
+    /// Histograms built with the same seed will retain the same values when updated identically.
+    ///
+    /// Defaults to a random seed. Without the `std` feature there is no source of entropy, so the
+    /// default seed is fixed.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets whether the histogram decays values based on the number of updates rather than the
+    /// passage of time.
+    ///
+    /// With a logical clock, each call to [`ExponentialDecayHistogram::update`] or
+    /// [`ExponentialDecayHistogram::update_many`] advances the histogram's time by one second
+    /// instead of consulting its [`Clock`]. The alpha is then the decay rate per update, and the
+    /// rescale threshold is measured in updates. The `*_at` methods still insert values at the
+    /// specified time, and shouldn't be mixed with a logical clock.
+    ///
+    /// Defaults to `false`.
+    pub fn logical_clock(&mut self, logical_clock: bool) -> &mut Self {
+        self.logical_clock = logical_clock;
+        self
+    }
+
+    /// Sets a predicate which values must pass to be recorded by the histogram.
+    ///
+    /// Values rejected by the filter are dropped by the `update` methods without affecting the
+    /// histogram's count or reservoir. This is useful to exclude sentinel values such as `-1` for
+    /// "no measurement" without checking for them at every call site. The filter is not applied to
+    /// values passed to [`Builder::samples`] or merged from other histograms, and is not
+    /// serialized.
+    ///
+    /// The filter is stored in an `Arc`, so a histogram is 16 bytes larger than it would be without
+    /// support for filters, whether or not one is set.
+    ///
+    /// Defaults to no filter.
+    pub fn filter<F>(&mut self, filter: F) -> &mut Self
+    where
+        F: Fn(T) -> bool + Sync + Send + 'static,
+    {
+        self.filter = Some(Filter(Arc::new(filter)));
+        self
+    }
+
+    /// Sets values with which to pre-populate the histogram.
+    ///
+    /// Each value is inserted at the histogram's construction time with the specified weight,
+    /// which is relative to the other values. If there are more values than the histogram's size,
+    /// only the highest weight values are retained. The histogram's count is set to the number of
+    /// values.
+    ///
+    /// Combined with [`Snapshot::raw_samples`], this can restore a histogram from a snapshot:
+    ///
+    /// ```
+    /// use exponential_decay_histogram::ExponentialDecayHistogram;
+    ///
+    /// let mut histogram = ExponentialDecayHistogram::new();
+    /// for i in 0..100 {
+    ///     histogram.update(i);
+    /// }
+    /// let snapshot = histogram.snapshot();
+    ///
+    /// let restored = ExponentialDecayHistogram::builder()
+    ///     .samples(snapshot.raw_samples())
+    ///     .build();
+    /// assert_eq!(restored.snapshot().max(), snapshot.max());
+    /// ```
+    ///
+    /// This overrides any previous call to [`Builder::samples`].
+    ///
+    /// Building the histogram fails if any weight is not positive and finite, or is too large to be
+    /// stored.
+    pub fn samples<I>(&mut self, samples: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (T, f64)>,
+    {
+        self.samples.clear();
+        self.samples.extend(samples);
+        self
+    }
+
+    /// Creates a new [`ExponentialDecayHistogram`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the builder's configuration is invalid. See [`Builder::try_build`] for details.
+    pub fn build(&self) -> ExponentialDecayHistogram<T> {
+        match self.try_build() {
+            Ok(histogram) => histogram,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Creates a new [`ExponentialDecayHistogram`], returning an error if the builder's
+    /// configuration is invalid.
+    ///
+    /// The size and rescale threshold must be nonzero, the alpha must be non-negative and finite,
+    /// and the weights of any [samples](Builder::samples) must be positive, finite, and small
+    /// enough to be stored.
+    pub fn try_build(&self) -> Result<ExponentialDecayHistogram<T>, BuilderError> {
+        // using a SmallRng is ~10% faster than using thread_rng()
+        let rng = match self.seed {
+            Some(seed) => SmallRng::seed_from_u64(seed),
+            #[cfg(feature = "std")]
+            None => SmallRng::from_rng(&mut rand::rng()),
+            #[cfg(not(feature = "std"))]
+            None => SmallRng::seed_from_u64(0),
+        };
+
+        self.try_build_with_rng(rng)
+    }
+
+    /// Creates a new [`ExponentialDecayHistogram`] which uses the specified random number
+    /// generator to sample values.
+    ///
+    /// The histogram's sampling decisions are entirely determined by the generator, so a
+    /// generator with a fixed seed produces reproducible results. The builder's
+    /// [seed](Builder::seed) is ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the builder's configuration is invalid. See [`Builder::try_build`] for details.
+    pub fn build_with_rng<R>(&self, rng: R) -> ExponentialDecayHistogram<T, R>
+    where
+        R: Rng,
+    {
+        match self.try_build_with_rng(rng) {
+            Ok(histogram) => histogram,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Creates a new [`ExponentialDecayHistogram`] which uses the specified random number
+    /// generator to sample values, returning an error if the builder's configuration is invalid.
+    ///
+    /// See [`Builder::build_with_rng`] and [`Builder::try_build`] for details.
+    pub fn try_build_with_rng<R>(
+        &self,
+        rng: R,
+    ) -> Result<ExponentialDecayHistogram<T, R>, BuilderError>
+    where
+        R: Rng,
+    {
+        if self.size == 0 {
+            return Err(BuilderError::ZeroSize);
+        }
+        if !(self.alpha >= 0. && self.alpha.is_finite()) {
+            return Err(BuilderError::InvalidAlpha);
+        }
+        if self.rescale_threshold == Duration::from_secs(0) {
+            return Err(BuilderError::ZeroRescaleThreshold);
+        }
+        if self.start_elapsed > self.rescale_threshold {
+            return Err(BuilderError::InvalidStartElapsed);
+        }
+        let max_weight = max_weight(self.size);
+        if !self
+            .samples
+            .iter()
+            .all(|&(_, weight)| weight > 0. && weight <= max_weight)
+        {
+            return Err(BuilderError::InvalidSampleWeight);
+        }
+
+        let now = self.now.unwrap_or_else(|| self.clock.now());
+        let start_time = now
+            .checked_sub(self.start_elapsed)
+            .ok_or(BuilderError::InvalidStartElapsed)?;
+
+        let mut histogram = ExponentialDecayHistogram {
+            values: BinaryHeap::new(),
+            alpha: self.alpha,
+            size: self.size,
+            count: 0,
+            evicted: 0,
+            epoch: now,
+            start_time,
+            // we store this explicitly because it's ~10% faster than doing the math on demand
+            next_scale_time: saturating_add(start_time, self.rescale_threshold),
+            rescale_threshold: self.rescale_threshold,
+            clock: self.clock.clone(),
+            logical_time: if self.logical_clock { Some(now) } else { None },
+            filter: self.filter.clone(),
+            rng,
+        };
+
+        if !self.samples.is_empty() {
+            let mut samples = self.samples.clone();
+            samples.sort_by(|a, b| b.1.total_cmp(&a.1));
+            for &(value, weight) in samples.iter().take(self.size) {
+                histogram.insert_at(now, value, weight);
+            }
+            histogram.count = samples.len() as u64;
+            histogram.evicted = samples.len().saturating_sub(self.size) as u64;
+        }
+
+        Ok(histogram)
+    }
+}
+
+/// An error returned when building an [`ExponentialDecayHistogram`] with an invalid configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuilderError {
+    /// The size was 0.
+    ZeroSize,
+    /// The alpha was negative, NaN, or infinite.
+    InvalidAlpha,
+    /// The rescale threshold was 0.
+    ZeroRescaleThreshold,
+    /// The weight of a sample was not positive and finite, or was too large to be stored.
+    InvalidSampleWeight,
+    /// The start elapsed time was greater than the rescale threshold, or placed the decay landmark
+    /// before the earliest representable time.
+    InvalidStartElapsed,
+}
+
+impl fmt::Display for BuilderError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            BuilderError::ZeroSize => "histogram size must be positive",
+            BuilderError::InvalidAlpha => "alpha must be non-negative and finite",
+            BuilderError::ZeroRescaleThreshold => "histogram rescale threshold must be positive",
+            BuilderError::InvalidSampleWeight => "sample weight must be positive and finite",
+            BuilderError::InvalidStartElapsed => {
+                "histogram start elapsed time must not exceed the rescale threshold"
+            }
+        };
+        fmt.write_str(s)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for BuilderError {}
+
+#[derive(Debug, PartialEq)]
+struct SnapshotEntry<T> {
+    value: T,
+    norm_weight: f64,
+    quantile: NotNan<f64>,
+}
+
+/// A snapshot of the state of an `ExponentialDecayHistogram` at some point in time.
+#[derive(Debug)]
+pub struct Snapshot<T = i64> {
+    entries: Vec<SnapshotEntry<T>>,
+    count: u64,
+    mean: f64,
+    total_weight: f64,
+}
+
+/// Returns an empty snapshot.
+impl<T> Default for Snapshot<T> {
+    fn default() -> Snapshot<T> {
+        Snapshot {
+            entries: vec![],
+            count: 0,
+            mean: 0.,
+            total_weight: 0.,
+        }
+    }
+}
+
+/// Snapshots are equal if they have the same count and the same values with exactly the same
+/// normalized weights.
+impl<T> PartialEq for Snapshot<T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Snapshot<T>) -> bool {
+        self.count == other.count && self.entries == other.entries
+    }
+}
+
+/// Formats a one-line summary of the statistics of the snapshot.
+impl<T> fmt::Display for Snapshot<T>
+where
+    T: Value + fmt::Display,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(fmt, "count={} (empty)", self.count);
+        }
+
+        let summary = self.summary();
+        write!(
+            fmt,
+            "count={} min={} max={} mean={:.2} stddev={:.2} median={}",
+            summary.count, summary.min, summary.max, summary.mean, summary.stddev, summary.median,
+        )
+    }
+}
+
+impl<T> Snapshot<T>
+where
+    T: Value,
+{
+    /// Returns the value at a specified quantile in the snapshot, or 0 if it is
+    /// empty.
+    ///
+    /// For example, `snapshot.value(0.5)` returns the median value of the
+    /// snapshot.
+    ///
+    /// Each value in the snapshot covers a range of quantiles starting at the total weight of the
+    /// values below it. The first value whose range starts at or after `quantile` is returned, or
+    /// the largest value if there is none. If several values start at the same quantile, which can
+    /// happen when their weights have decayed to 0, the smallest of them is returned. A quantile of
+    /// 0 always returns the smallest value and a quantile of 1 the largest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quantile` is not between 0 and 1 (inclusive).
+    pub fn value(&self, quantile: f64) -> T {
+        self.try_value(quantile).unwrap_or_default()
+    }
+
+    /// Returns the value at a specified quantile in the snapshot, or `None` if
+    /// it is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quantile` is not between 0 and 1 (inclusive).
+    pub fn try_value(&self, quantile: f64) -> Option<T> {
+        assert!((0. ..=1.).contains(&quantile));
+
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        Some(self.entries[self.quantile_index(0, quantile)].value)
+    }
+
+    // Returns the index of the first entry at or after `start` whose range starts at or after
+    // `quantile`, clamped to the last entry.
+    fn quantile_index(&self, start: usize, quantile: f64) -> usize {
+        let last = self.entries.len() - 1;
+        // entries whose weights have underflowed to 0 can start exactly at 1
+        if quantile == 1. {
+            return last;
+        }
+
+        let idx = start + self.entries[start..].partition_point(|e| *e.quantile < quantile);
+        idx.min(last)
+    }
+
+    /// Returns `true` if the snapshot retains enough values to estimate the value at a specified
+    /// quantile.
+    ///
+    /// An estimate of an extreme quantile is only as good as the number of values beyond it. The
+    /// quantile is considered reliable if the effective number of values on its sparser side,
+    /// `min(q, 1 - q) * n`, is at least 5, where `n` is the [`Snapshot::effective_sample_size`].
+    /// For example, p99 requires an effective sample size of at least 500, and p99.9 at least
+    /// 5000. The quantiles 0 and 1 are never considered reliable, since the extreme values
+    /// retained by the snapshot say little about those of the underlying distribution.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quantile` is not between 0 and 1 (inclusive).
+    pub fn quantile_is_reliable(&self, quantile: f64) -> bool {
+        assert!((0. ..=1.).contains(&quantile));
+
+        let tail = quantile.min(1. - quantile);
+        tail * self.effective_sample_size() >= MIN_TAIL_SAMPLES
+    }
+
+    /// Returns a linearly interpolated value at a specified quantile in the snapshot, or 0 if it is
+    /// empty.
+    ///
+    /// Each entry in the snapshot is positioned at the midpoint of the range of quantiles it
+    /// covers: `p_i = q_i + w_i / 2`, where `q_i` is the total weight of the entries below it and
+    /// `w_i` is its own weight. For a quantile `q` between the positions of adjacent entries `i`
+    /// and `i + 1`, the result is `v_i + (v_{i+1} - v_i) * (q - p_i) / (p_{i+1} - p_i)`.
+    /// Quantiles below the first position or above the last are clamped to the smallest and
+    /// largest values respectively. Entries whose weights have underflowed to 0 are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quantile` is not between 0 and 1 (inclusive).
+    pub fn interpolated_value(&self, quantile: f64) -> f64 {
+        assert!((0. ..=1.).contains(&quantile));
+
+        let position = |e: &SnapshotEntry<T>| *e.quantile + e.norm_weight / 2.;
+        // values whose weights have underflowed to 0 don't contribute to the distribution
+        let weighted = |e: &&SnapshotEntry<T>| e.norm_weight > 0.;
+
+        let idx = self.entries.partition_point(|e| position(e) <= quantile);
+        let lower = self.entries[..idx].iter().rev().find(weighted);
+        let upper = self.entries[idx..].iter().find(weighted);
+
+        let (lower, upper) = match (lower, upper) {
+            (Some(lower), Some(upper)) => (lower, upper),
+            (Some(entry), None) | (None, Some(entry)) => return entry.value.to_f64(),
+            (None, None) => return self.entries.first().map_or(0., |e| e.value.to_f64()),
+        };
+
+        let (lower_position, upper_position) = (position(lower), position(upper));
+        let (lower_value, upper_value) = (lower.value.to_f64(), upper.value.to_f64());
+        if upper_position <= lower_position {
+            return lower_value;
+        }
+
+        lower_value
+            + (upper_value - lower_value) * (quantile - lower_position)
+                / (upper_position - lower_position)
+    }
+
+    /// Returns an approximate confidence interval for the value at a specified quantile in the
+    /// snapshot, or `(0, 0)` if it is empty.
+    ///
+    /// The snapshot is treated as a random sample of [`Snapshot::effective_sample_size`] values
+    /// `n`. The rank of the true `quantile` within such a sample is binomially distributed, which
+    /// is approximated by a normal distribution with a standard deviation of
+    /// `sqrt(quantile * (1 - quantile) / n)`. The returned values are those at the quantiles `z`
+    /// standard deviations below and above `quantile`, where `z` is chosen to cover the
+    /// `confidence` level. The approximation assumes the values are independent and is
+    /// unreliable for small samples or extreme quantiles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quantile` is not between 0 and 1 (inclusive) or `confidence` is not between 0
+    /// and 1 (exclusive).
+    pub fn quantile_confidence_interval(&self, quantile: f64, confidence: f64) -> (T, T) {
+        assert!((0. ..=1.).contains(&quantile));
+        assert!(0. < confidence && confidence < 1.);
+
+        if self.entries.is_empty() {
+            return (T::default(), T::default());
+        }
+
+        let z = normal_quantile((1. + confidence) / 2.);
+        let margin = z * (quantile * (1. - quantile) / self.effective_sample_size()).sqrt();
+
+        (
+            self.value((quantile - margin).max(0.)),
+            self.value((quantile + margin).min(1.)),
+        )
+    }
+
+    /// Returns the values at each of the specified quantiles in the snapshot.
+    ///
+    /// This is equivalent to calling [`Snapshot::value`] for each quantile, but each search only
+    /// considers the entries after the result for the previous, smaller quantile. The returned
+    /// values are in the same order as `quantiles`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any quantile is not between 0 and 1 (inclusive).
+    pub fn quantiles(&self, quantiles: &[f64]) -> Vec<T> {
+        for quantile in quantiles {
+            assert!((0. ..=1.).contains(quantile));
+        }
+
+        let mut values = vec![T::default(); quantiles.len()];
+        if self.entries.is_empty() {
+            return values;
+        }
+
+        let mut order = (0..quantiles.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| quantiles[a].total_cmp(&quantiles[b]));
+
+        // the quantiles are visited in order, so each search can start from the previous result
+        let mut idx = 0;
+        for i in order {
+            idx = self.quantile_index(idx, quantiles[i]);
+            values[i] = self.entries[idx].value;
+        }
+
+        values
+    }
+
+    /// Returns the values at evenly spaced quantiles in the snapshot, or 0s if it is empty.
+    ///
+    /// The quantiles are 0, `step`, `2 * step`, and so on, with a final quantile of 1 even if it
+    /// is not a multiple of `step`. For example, a step of 0.1 produces the deciles. Each quantile
+    /// is returned along with its value.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `0 < step <= 1`.
+    pub fn value_table(&self, step: f64) -> Vec<(f64, T)> {
+        assert!(0. < step && step <= 1.);
+
+        // allow for rounding error when 1 is a multiple of the step
+        let steps = (1. / step - 1e-9).ceil() as usize;
+        let quantiles = (0..=steps)
+            .map(|i| if i == steps { 1. } else { i as f64 * step })
+            .collect::<Vec<_>>();
+        let values = self.quantiles(&quantiles);
+
+        quantiles.into_iter().zip(values).collect()
+    }
+
+    /// Returns the fraction of the weighted distribution of the snapshot at or below a value, or 0
+    /// if it is empty.
+    ///
+    /// This is the inverse of [`Snapshot::value`]. Values below the smallest value in the snapshot
+    /// have a rank of 0, and values at or above the largest have a rank of 1. It is equivalent to
+    /// [`Snapshot::cdf`].
+    pub fn rank(&self, value: T) -> f64 {
+        self.cdf(value)
+    }
+
+    /// Returns the ranks of each of the specified values in the snapshot.
+    ///
+    /// This is equivalent to calling [`Snapshot::rank`] for each value, but each search only
+    /// considers the entries after the result for the previous, smaller value. The returned ranks
+    /// are in the same order as `values`.
+    pub fn ranks(&self, values: &[T]) -> Vec<f64> {
+        let mut ranks = vec![0.; values.len()];
+
+        let mut order = (0..values.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| values[i]);
+
+        let mut idx = 0;
+        for i in order {
+            idx += self.entries[idx..].partition_point(|e| e.value <= values[i]);
+            ranks[i] = self.cdf_at(idx);
+        }
+
+        ranks
+    }
+
+    /// Returns the cumulative distribution function of the snapshot evaluated at a value, or 0 if
+    /// it is empty.
+    ///
+    /// This is the total normalized weight of the values in the snapshot which are less than or
+    /// equal to `value`. For a value between two values in the snapshot, it is the weight up to
+    /// and including the lower of the two. The result is clamped to the range `[0, 1]`.
+    pub fn cdf(&self, value: T) -> f64 {
+        let idx = self.entries.partition_point(|e| e.value <= value);
+        self.cdf_at(idx)
+    }
+
+    /// Returns the fraction of the weighted distribution of the snapshot with values in the range
+    /// `[low, high)`, or 0 if it is empty.
+    ///
+    /// This is the total normalized weight of the values which are at least `low` and less than
+    /// `high`, so the proportions of adjacent ranges add up. For example, the fraction of requests
+    /// which took between 100 and 500 milliseconds is `snapshot.proportion_in_range(100, 500)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `low` is greater than `high`.
+    pub fn proportion_in_range(&self, low: T, high: T) -> f64 {
+        assert!(low <= high, "low must not be greater than high");
+
+        let low = self.entries.partition_point(|e| e.value < low);
+        let high = low + self.entries[low..].partition_point(|e| e.value < high);
+        (self.cdf_at(high) - self.cdf_at(low)).max(0.)
+    }
+
+    // Returns the cumulative weight of the entries before `idx`.
+    fn cdf_at(&self, idx: usize) -> f64 {
+        if idx == 0 {
+            return 0.;
+        }
+        if idx == self.entries.len() {
+            return 1.;
+        }
+
+        let entry = &self.entries[idx - 1];
+        (*entry.quantile + entry.norm_weight).clamp(0., 1.)
+    }
+
+    /// Returns Tukey's fences for outliers in the snapshot, or `(0, 0)` if it is empty.
+    ///
+    /// With `q1` and `q3` the values at the 0.25 and 0.75 quantiles, the fences are
+    /// `q1 - 1.5 * (q3 - q1)` and `q3 + 1.5 * (q3 - q1)`. Values outside of them are commonly
+    /// considered outliers.
+    pub fn outlier_bounds(&self) -> (f64, f64) {
+        let q1 = self.value(0.25).to_f64();
+        let q3 = self.value(0.75).to_f64();
+        let iqr = q3 - q1;
+
+        (q1 - 1.5 * iqr, q3 + 1.5 * iqr)
+    }
+
+    /// Returns the distinct values in the snapshot paired with the cumulative distribution
+    /// function evaluated at each of them, in ascending order of value.
+    ///
+    /// Each pair is equivalent to `(value, snapshot.cdf(value))`, so the weight of the final value
+    /// is 1.
+    pub fn cdf_points(&self) -> Vec<(T, f64)> {
+        let mut points = Vec::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            match self.entries.get(i + 1) {
+                Some(next) if next.value == entry.value => {}
+                _ => points.push((entry.value, (*entry.quantile + entry.norm_weight).min(1.))),
+            }
+        }
+
+        if let Some(last) = points.last_mut() {
+            last.1 = 1.;
+        }
+
+        points
+    }
+
+    /// Returns the largest value in the snapshot, or 0 if it is empty.
+    pub fn max(&self) -> T {
+        self.try_max().unwrap_or_default()
+    }
+
+    /// Returns the largest value in the snapshot, or `None` if it is empty.
+    pub fn try_max(&self) -> Option<T> {
+        self.entries.last().map(|e| e.value)
+    }
+
+    /// Returns the smallest value in the snapshot, or 0 if it is empty.
+    pub fn min(&self) -> T {
+        self.try_min().unwrap_or_default()
+    }
+
+    /// Returns the smallest value in the snapshot, or `None` if it is empty.
+    pub fn try_min(&self) -> Option<T> {
+        self.entries.first().map(|e| e.value)
+    }
+
+    /// Returns the value with the largest total weight in the snapshot, or `None` if it is empty.
+    ///
+    /// Equal values are coalesced as in [`Snapshot::values`]. If multiple values have the same
+    /// weight, the smallest of them is returned.
+    pub fn mode(&self) -> Option<T> {
+        let mut mode = None;
+        let mut max_weight = 0.;
+        for (value, weight) in self.values() {
+            if mode.is_none() || weight > max_weight {
+                mode = Some(value);
+                max_weight = weight;
+            }
+        }
+
+        mode
+    }
+
+    /// Returns the mean of the values in the snapshot, or 0 if it is empty.
+    pub fn mean(&self) -> f64 {
+        self.try_mean().unwrap_or(0.)
+    }
+
+    /// Returns the mean of the values in the snapshot, or `None` if it is empty.
+    pub fn try_mean(&self) -> Option<f64> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        Some(self.mean)
+    }
+
+    /// Returns the weighted geometric mean of the positive values in the snapshot, or 0 if there
+    /// are none.
+    ///
+    /// The geometric mean is undefined for values less than or equal to 0, so they are ignored and
+    /// the mean is computed over the remaining weight.
+    pub fn geometric_mean(&self) -> f64 {
+        let mut sum = 0.;
+        let mut sum_weight = 0.;
+        for entry in self.positive_entries() {
+            sum += entry.norm_weight * entry.value.to_f64().ln();
+            sum_weight += entry.norm_weight;
+        }
+
+        if sum_weight == 0. {
+            return 0.;
+        }
+
+        (sum / sum_weight).exp()
+    }
+
+    /// Returns the weighted harmonic mean of the positive values in the snapshot, or 0 if there
+    /// are none.
+    ///
+    /// Like [`Snapshot::geometric_mean`], values less than or equal to 0 are ignored.
+    pub fn harmonic_mean(&self) -> f64 {
+        let mut sum = 0.;
+        let mut sum_weight = 0.;
+        for entry in self.positive_entries() {
+            sum += entry.norm_weight / entry.value.to_f64();
+            sum_weight += entry.norm_weight;
+        }
+
+        if sum_weight == 0. {
+            return 0.;
+        }
+
+        sum_weight / sum
+    }
+
+    fn positive_entries(&self) -> impl Iterator<Item = &SnapshotEntry<T>> {
+        self.entries.iter().filter(|e| e.value.to_f64() > 0.)
+    }
+
+    /// Returns the mean of the values in the snapshot between two quantiles, or 0 if it is empty.
+    ///
+    /// The weight of the distribution below the `lower` quantile and above the `upper` quantile is
+    /// discarded, and the weighted mean of the remainder is returned. For example,
+    /// `snapshot.trimmed_mean(0.05, 0.95)` ignores the outer 10% of the distribution.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `0 <= lower < upper <= 1`.
+    pub fn trimmed_mean(&self, lower: f64, upper: f64) -> f64 {
+        assert!(0. <= lower && lower < upper && upper <= 1.);
+
+        let mut sum = 0.;
+        let mut sum_weight = 0.;
+        let mut range = None;
+        for entry in &self.entries {
+            let start = entry.quantile.into_inner().max(lower);
+            let end = (*entry.quantile + entry.norm_weight).min(upper);
+            if start < end {
+                sum += entry.value.to_f64() * (end - start);
+                sum_weight += end - start;
+                let min = range.map_or(entry.value, |(min, _)| min);
+                range = Some((min, entry.value));
+            }
+        }
+
+        let (min, max) = match range {
+            Some(range) => range,
+            None => return 0.,
+        };
+
+        // guard against rounding outside of the range of the included values
+        (sum / sum_weight).clamp(min.to_f64(), max.to_f64())
+    }
+
+    /// Returns the mean of the values in the snapshot with its tails clamped to two quantiles, or
+    /// 0 if it is empty.
+    ///
+    /// Values below the value at the `lower` quantile are replaced by that value, and values above
+    /// the value at the `upper` quantile are replaced by that value. Unlike
+    /// [`Snapshot::trimmed_mean`], the weight of the tails is retained, so the weighted mean is
+    /// taken over the entire distribution.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `0 <= lower < upper <= 1`.
+    pub fn winsorized_mean(&self, lower: f64, upper: f64) -> f64 {
+        assert!(0. <= lower && lower < upper && upper <= 1.);
+
+        if self.entries.is_empty() {
+            return 0.;
+        }
+
+        let (min, max) = (self.value(lower), self.value(upper));
+        compensated_sum(
+            self.entries
+                .iter()
+                .map(|e| e.value.clamp(min, max).to_f64() * e.norm_weight),
+        )
+        .clamp(min.to_f64(), max.to_f64())
+    }
+
+    /// Returns an estimate of the sum of all values written to the histogram, or 0 if it is empty.
+    ///
+    /// This is the weighted mean of the snapshot multiplied by its count. Since the histogram only
+    /// retains a decay-weighted sample of its values, this is an approximation which favors recent
+    /// values in the same way as the other statistics.
+    pub fn weighted_sum(&self) -> f64 {
+        self.mean() * self.count as f64
+    }
+
+    /// Returns the sum of the values retained in the snapshot, or 0 if it is empty.
+    ///
+    /// Values are not weighted. Once more values have been written to the histogram than it can
+    /// store, this only covers the sampled subset of them.
+    pub fn sample_sum(&self) -> f64 {
+        self.entries.iter().map(|e| e.value.to_f64()).sum()
+    }
+
+    /// Returns the standard deviation of the values in the snapshot, or 0 if it
+    /// is empty.
+    ///
+    /// This is the population standard deviation - see [`Snapshot::stddev_with`].
+    pub fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Returns the standard deviation of the values in the snapshot with the specified bias
+    /// correction, or 0 if it has fewer than 2 values.
+    ///
+    /// This is the square root of [`Snapshot::variance_with`].
+    pub fn stddev_with(&self, bias: Bias) -> f64 {
+        self.variance_with(bias).sqrt()
+    }
+
+    /// Returns the variance of the values in the snapshot, or 0 if it is empty.
+    ///
+    /// This is the population variance - see [`Snapshot::variance_with`].
+    pub fn variance(&self) -> f64 {
+        self.variance_with(Bias::Population)
+    }
+
+    /// Returns the variance of the values in the snapshot with the specified bias correction, or 0
+    /// if it has fewer than 2 values.
+    ///
+    /// The population variance is `Σ w_i (x_i - μ)²` over the normalized weights `w_i`. The sample
+    /// variance applies Bessel's correction using the [effective sample size] `n` in place of the
+    /// number of values, multiplying the population variance by `n / (n - 1)`. It is 0 if `n` is
+    /// not greater than 1.
+    ///
+    /// [effective sample size]: Snapshot::effective_sample_size
+    pub fn variance_with(&self, bias: Bias) -> f64 {
+        if self.entries.len() <= 1 {
+            return 0.;
+        }
+
+        let mut variance = compensated_sum(self.entries.iter().map(|e| {
+            let diff = e.value.to_f64() - self.mean;
+            e.norm_weight * diff * diff
+        }));
+
+        if bias == Bias::Sample {
+            let n = self.effective_sample_size();
+            if n <= 1. {
+                return 0.;
+            }
+            variance *= n / (n - 1.);
+        }
+
+        variance
+    }
+
+    /// Returns the coefficient of variation of the values in the snapshot, or 0 if their mean is 0.
+    ///
+    /// This is the ratio of the standard deviation to the mean. Since it is dimensionless, it can
+    /// be used to compare the spread of distributions with different scales. It is negative if the
+    /// mean is negative.
+    pub fn coefficient_of_variation(&self) -> f64 {
+        if self.mean == 0. {
+            return 0.;
+        }
+
+        self.stddev() / self.mean
+    }
+
+    /// Returns the skewness of the values in the snapshot, or 0 if it has fewer than 2 values.
+    ///
+    /// This is the third standardized moment of the weighted distribution. It is positive when the
+    /// distribution has a long tail of large values, and negative for a long tail of small values.
+    pub fn skewness(&self) -> f64 {
+        let variance = self.central_moment(2);
+        if self.entries.len() <= 1 || variance == 0. {
+            return 0.;
+        }
+
+        self.central_moment(3) / variance.powf(1.5)
+    }
+
+    /// Returns the excess kurtosis of the values in the snapshot, or 0 if it has fewer than 2
+    /// values.
+    ///
+    /// This is the fourth standardized moment of the weighted distribution minus 3, so a normal
+    /// distribution has an excess kurtosis of 0. Heavy-tailed distributions have a positive
+    /// kurtosis.
+    pub fn kurtosis(&self) -> f64 {
+        let variance = self.central_moment(2);
+        if self.entries.len() <= 1 || variance == 0. {
+            return 0.;
+        }
+
+        self.central_moment(4) / (variance * variance) - 3.
+    }
+
+    /// Returns the median absolute deviation of the values in the snapshot, or 0 if it has fewer
+    /// than 2 values.
+    ///
+    /// This is the weighted median of the absolute differences between each value and the median
+    /// value of the snapshot. Unlike [`Snapshot::stddev`], it is not sensitive to a small number
+    /// of extreme values.
+    pub fn mad(&self) -> f64 {
+        if self.entries.len() <= 1 {
+            return 0.;
+        }
+
+        let median = self.value(0.5).to_f64();
+        let mut deviations = self
+            .entries
+            .iter()
+            .map(|e| ((e.value.to_f64() - median).abs(), e.norm_weight))
+            .collect::<Vec<_>>();
+        deviations.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // this matches the quantile lookup performed by Snapshot::value
+        let mut quantile = 0.;
+        for &(deviation, weight) in &deviations {
+            if quantile >= 0.5 {
+                return deviation;
+            }
+            quantile += weight;
+        }
+
+        deviations[deviations.len() - 1].0
+    }
+
+    fn central_moment(&self, k: i32) -> f64 {
+        compensated_sum(
+            self.entries
+                .iter()
+                .map(|e| e.norm_weight * (e.value.to_f64() - self.mean).powi(k)),
+        )
+    }
+
+    /// Returns the number of values which have been written to the histogram at
+    /// the time of the snapshot.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the number of values retained in the snapshot.
+    ///
+    /// Unlike [`Snapshot::count`], this is bounded by the size of the histogram.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the snapshot contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the total weight of the values in the snapshot before normalization, or 0 if it is
+    /// empty.
+    ///
+    /// The weights reported by [`Snapshot::values`] are normalized to sum to 1. This total depends
+    /// on how recently the histogram's decay landmark was reset, and is mostly useful to diagnose
+    /// numeric issues.
+    pub fn total_weight(&self) -> f64 {
+        self.total_weight
+    }
+
+    /// Returns Kish's effective sample size of the snapshot, or 0 if it is empty.
+    ///
+    /// This is `(Σw)² / Σw²` over the weights of the values in the snapshot. It is equal to
+    /// [`Snapshot::len`] when all values have the same weight, and shrinks as the weights become
+    /// more skewed towards recent values. The ratio is independent of the scale of the weights, so
+    /// the normalized weights are used.
+    pub fn effective_sample_size(&self) -> f64 {
+        if self.entries.is_empty() {
+            return 0.;
+        }
+
+        let sum_squares = self
+            .entries
+            .iter()
+            .map(|e| e.norm_weight * e.norm_weight)
+            .sum::<f64>();
+
+        1. / sum_squares
+    }
+
+    /// Returns `true` if the snapshot is approximately equal to another.
+    ///
+    /// The snapshots must have the same count and the same values, but their normalized weights
+    /// and quantiles may differ by a relative tolerance: two floats `a` and `b` are considered
+    /// equal if `|a - b| <= tolerance * max(|a|, |b|)`. A tolerance of 0 is equivalent to `==`.
+    pub fn approx_eq(&self, other: &Snapshot<T>, tolerance: f64) -> bool {
+        let float_eq = |a: f64, b: f64| (a - b).abs() <= tolerance * a.abs().max(b.abs());
+
+        self.count == other.count
+            && self.entries.len() == other.entries.len()
+            && self.entries.iter().zip(&other.entries).all(|(a, b)| {
+                a.value == b.value
+                    && float_eq(a.norm_weight, b.norm_weight)
+                    && float_eq(*a.quantile, *b.quantile)
+            })
+    }
+
+    /// Returns the differences between the statistics of this snapshot and a baseline snapshot.
+    ///
+    /// Each difference is this snapshot's statistic minus the baseline's, so a positive difference
+    /// means the statistic has increased relative to the baseline. The values at each of
+    /// `quantiles` are compared, in the same order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any quantile is not between 0 and 1 (inclusive).
+    pub fn diff(&self, baseline: &Snapshot<T>, quantiles: &[f64]) -> SnapshotDiff {
+        let values = self.quantiles(quantiles);
+        let baseline_values = baseline.quantiles(quantiles);
+
+        SnapshotDiff {
+            count: i128::from(self.count) - i128::from(baseline.count),
+            mean: self.mean - baseline.mean,
+            stddev: self.stddev() - baseline.stddev(),
+            quantiles: quantiles
+                .iter()
+                .zip(values.iter().zip(&baseline_values))
+                .map(|(&q, (v, b))| (q, v.to_f64() - b.to_f64()))
+                .collect(),
+        }
+    }
+
+    /// Returns the rate at which the value at a quantile changed per second between a previous
+    /// snapshot and this one.
+    ///
+    /// This is `(self.value(quantile) - previous.value(quantile)) / elapsed`, where `elapsed` is
+    /// the time between the two snapshots. The rate is positive if the value has increased since
+    /// the previous snapshot, and negative if it has decreased.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quantile` is not between 0 and 1 (inclusive), or if `elapsed` is 0.
+    pub fn rate(&self, previous: &Snapshot<T>, elapsed: Duration, quantile: f64) -> f64 {
+        assert!(
+            elapsed > Duration::from_secs(0),
+            "elapsed time must be positive"
+        );
+
+        let change = self.value(quantile).to_f64() - previous.value(quantile).to_f64();
+        change / elapsed.as_secs_f64()
+    }
+
+    /// Returns a summary of the statistics of the snapshot.
+    pub fn summary(&self) -> Summary<T> {
+        Summary {
+            count: self.count,
+            min: self.min(),
+            max: self.max(),
+            mean: self.mean,
+            stddev: self.stddev(),
+            median: self.value(0.5),
+        }
+    }
+
+    /// Returns a summary of the statistics of the snapshot including the values at each of
+    /// `quantiles`.
+    ///
+    /// The summary is much smaller than the snapshot itself, so it's cheaper to store or send to a
+    /// metrics backend.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any quantile is not between 0 and 1 (inclusive).
+    pub fn summary_stats(&self, quantiles: &[f64]) -> SummaryStats<T> {
+        SummaryStats {
+            count: self.count,
+            min: self.min(),
+            max: self.max(),
+            mean: self.mean,
+            stddev: self.stddev(),
+            quantiles: quantiles
+                .iter()
+                .copied()
+                .zip(self.quantiles(quantiles))
+                .collect(),
+        }
+    }
+
+    /// Returns an iterator over the distinct values in the snapshot along with their weights.
+    pub fn values(&self) -> Values<'_, T> {
+        Values {
+            entries: &self.entries,
+        }
+    }
+
+    /// Returns an iterator over the individual values retained in the snapshot along with their
+    /// raw weights.
+    ///
+    /// Unlike [`Snapshot::values`], equal values are not coalesced, and the weights are not
+    /// normalized - each is the decay weight of the sample when the snapshot was taken, so they sum
+    /// to [`Snapshot::total_weight`]. As with that total, the scale of the weights depends on the
+    /// histogram's decay landmark, so only their relative sizes are meaningful.
+    ///
+    /// Values are yielded in ascending order.
+    pub fn raw_samples(&self) -> RawSamples<'_, T> {
+        RawSamples {
+            entries: self.entries.iter(),
+            total_weight: self.total_weight,
+        }
+    }
+}
+
+impl Snapshot<i64> {
+    /// Returns the difference between the largest and smallest values in the snapshot, or 0 if it
+    /// is empty.
+    ///
+    /// The subtraction saturates at `i64::MAX` if the difference doesn't fit in an `i64`.
+    pub fn range(&self) -> i64 {
+        self.max().saturating_sub(self.min())
+    }
+
+    /// Returns the interquartile range of the snapshot, or 0 if it is empty.
+    ///
+    /// This is the difference between the values at the 0.75 and 0.25 quantiles. The subtraction
+    /// saturates at `i64::MAX` if the difference doesn't fit in an `i64`.
+    pub fn iqr(&self) -> i64 {
+        self.value(0.75).saturating_sub(self.value(0.25))
+    }
+
+    /// Returns the value in the snapshot closest to a target, or `None` if it is empty.
+    ///
+    /// If the target is equally close to two values, the smaller one is returned.
+    pub fn nearest(&self, target: i64) -> Option<i64> {
+        let idx = self.entries.partition_point(|e| e.value < target);
+        let above = self.entries.get(idx).map(|e| e.value);
+        let below = idx.checked_sub(1).map(|i| self.entries[i].value);
+
+        match (below, above) {
+            (Some(below), Some(above)) => {
+                if target.abs_diff(below) <= target.abs_diff(above) {
+                    Some(below)
+                } else {
+                    Some(above)
+                }
+            }
+            (below, above) => below.or(above),
+        }
+    }
+
+    /// Partitions the range of values in the snapshot into equal-width buckets.
+    ///
+    /// Each bucket is returned as an inclusive `(low, high, weight)` range of values along with
+    /// the total normalized weight of the values falling within it. The buckets cover the range
+    /// from the smallest to the largest value in the snapshot, and if that range contains fewer
+    /// than `num_buckets` integers, one bucket is returned per integer. An empty snapshot has no
+    /// buckets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_buckets` is 0.
+    pub fn to_buckets(&self, num_buckets: usize) -> Vec<(i64, i64, f64)> {
+        assert!(num_buckets > 0);
+
+        if self.is_empty() {
+            return vec![];
+        }
+
+        let min = i128::from(self.min());
+        let span = i128::from(self.max()) - min + 1;
+        let num_buckets = span.min(num_buckets as i128);
+
+        let bound = |i: i128| min + i * span / num_buckets;
+        let mut buckets = (0..num_buckets)
+            .map(|i| (bound(i) as i64, (bound(i + 1) - 1) as i64, 0.))
+            .collect::<Vec<_>>();
+
+        for (value, weight) in self.values() {
+            let idx = (i128::from(value) - min) * num_buckets / span;
+            buckets[idx as usize].2 += weight;
+        }
+
+        buckets
+    }
+
+    /// Partitions the values in the snapshot into logarithmically sized buckets.
+    ///
+    /// Each bucket is returned as an inclusive `(low, high, weight)` range of values along with
+    /// the total normalized weight of the values falling within it. The first bucket is an
+    /// underflow bucket covering all values below `min_value`, including any non-positive values.
+    /// It is followed by buckets whose lower bounds are `min_value * base^k` for `k = 0, 1, ...`,
+    /// rounded to the nearest integer, with each bucket extending up to one less than the next
+    /// bound. Buckets are always at least one integer wide, and are returned up to the one
+    /// containing the largest value in the snapshot. An empty snapshot has no buckets.
+    ///
+    /// At most 1024 buckets are returned. With a base close to 1, there may be more buckets than
+    /// that below the largest value, in which case the last bucket is extended to `i64::MAX`.
+    ///
+    /// For example, a base of 10 and a `min_value` of 1 produces the buckets `[i64::MIN, 0]`,
+    /// `[1, 9]`, `[10, 99]`, `[100, 999]`, and so on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `base` is not finite and greater than 1, or if `min_value` is not positive.
+    pub fn to_log_buckets(&self, base: f64, min_value: i64) -> Vec<(i64, i64, f64)> {
+        assert!(base > 1. && base.is_finite());
+        assert!(min_value > 0);
+
+        if self.is_empty() {
+            return vec![];
+        }
+
+        let max = self.max();
+        let mut buckets = vec![(i64::MIN, min_value - 1, 0.)];
+        let mut low = min_value;
+        let mut k = 1;
+        while low <= max {
+            let bound = (min_value as f64 * base.powi(k)).round();
+            let high = if bound >= i64::MAX as f64 || buckets.len() == MAX_LOG_BUCKETS - 1 {
+                i64::MAX
+            } else {
+                (bound as i64).max(low.saturating_add(1)) - 1
+            };
+            buckets.push((low, high, 0.));
+
+            if high == i64::MAX {
+                break;
+            }
+            low = high + 1;
+            k += 1;
+        }
+
+        let mut idx = 0;
+        for (value, weight) in self.values() {
+            while value > buckets[idx].1 {
+                idx += 1;
+            }
+            buckets[idx].2 += weight;
+        }
+
+        buckets
+    }
+}
+
+/// Methods for interpreting values recorded as a number of nanoseconds as `Duration`s.
+///
+/// Negative values are clamped to 0.
+impl Snapshot<i64> {
+    /// Returns the duration at a specified quantile in the snapshot, or 0 if it is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quantile` is not between 0 and 1 (inclusive).
+    pub fn value_duration(&self, quantile: f64) -> Duration {
+        nanos_to_duration(self.value(quantile))
+    }
+
+    /// Returns the largest duration in the snapshot, or 0 if it is empty.
+    pub fn max_duration(&self) -> Duration {
+        nanos_to_duration(self.max())
+    }
+
+    /// Returns the smallest duration in the snapshot, or 0 if it is empty.
+    pub fn min_duration(&self) -> Duration {
+        nanos_to_duration(self.min())
+    }
+
+    /// Returns the mean of the durations in the snapshot, or 0 if it is empty.
+    pub fn mean_duration(&self) -> Duration {
+        nanos_to_duration(self.mean().round() as i64)
+    }
+}
+
+/// The bias correction applied by [`Snapshot::stddev_with`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Bias {
+    /// The values are treated as the entire population.
+    Population,
+    /// The values are treated as a sample of a larger population, and Bessel's correction is
+    /// applied.
+    Sample,
+}
+
+/// A summary of the statistics of a [`Snapshot`].
+///
+/// Each field has the same value as the corresponding method on the snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary<T = i64> {
+    /// The number of values which have been written to the histogram.
+    pub count: u64,
+    /// The smallest value.
+    pub min: T,
+    /// The largest value.
+    pub max: T,
+    /// The mean of the values.
+    pub mean: f64,
+    /// The standard deviation of the values.
+    pub stddev: f64,
+    /// The median value.
+    pub median: T,
+}
+
+/// A compact summary of the statistics of a [`Snapshot`], suitable for storage or transmission.
+///
+/// This is returned by [`Snapshot::summary_stats`]. Unlike [`Summary`], it reports an arbitrary set
+/// of quantiles. With the `serde` Cargo feature, it implements `Serialize` and `Deserialize`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SummaryStats<T = i64> {
+    /// The number of values which have been written to the histogram.
+    pub count: u64,
+    /// The smallest value.
+    pub min: T,
+    /// The largest value.
+    pub max: T,
+    /// The mean of the values.
+    pub mean: f64,
+    /// The standard deviation of the values.
+    pub stddev: f64,
+    /// Each requested quantile along with the value at that quantile.
+    pub quantiles: Vec<(f64, T)>,
+}
+
+/// The differences between the statistics of two [`Snapshot`]s.
+///
+/// Each field is a snapshot's statistic minus that of the baseline it was compared to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotDiff {
+    /// The difference in the number of values written to the histograms.
+    pub count: i128,
+    /// The difference in the means of the values.
+    pub mean: f64,
+    /// The difference in the standard deviations of the values.
+    pub stddev: f64,
+    /// Each compared quantile along with the difference in the values at that quantile.
+    pub quantiles: Vec<(f64, f64)>,
+}
+
+/// An iterator over the distinct values in a snapshot along with their weights.
+pub struct Values<'a, T = i64> {
+    entries: &'a [SnapshotEntry<T>],
+}
+
+impl<T> Iterator for Values<'_, T>
+where
+    T: Value,
+{
+    type Item = (T, f64);
+
+    fn next(&mut self) -> Option<(T, f64)> {
+        let value = self.entries.first()?.value;
+        let len = self.entries.iter().take_while(|e| e.value == value).count();
+
+        let (run, rest) = self.entries.split_at(len);
+        self.entries = rest;
+
+        Some((value, run.iter().map(|e| e.norm_weight).sum()))
+    }
+}
+
+impl<T> DoubleEndedIterator for Values<'_, T>
+where
+    T: Value,
+{
+    fn next_back(&mut self) -> Option<(T, f64)> {
+        let value = self.entries.last()?.value;
+        let len = self
+            .entries
+            .iter()
+            .rev()
+            .take_while(|e| e.value == value)
+            .count();
+
+        let (rest, run) = self.entries.split_at(self.entries.len() - len);
+        self.entries = rest;
+
+        Some((value, run.iter().map(|e| e.norm_weight).sum()))
+    }
+}
+
+/// Returns an iterator over the distinct values in the snapshot along with their weights.
+impl<'a, T> IntoIterator for &'a Snapshot<T>
+where
+    T: Value,
+{
+    type Item = (T, f64);
+    type IntoIter = Values<'a, T>;
+
+    fn into_iter(self) -> Values<'a, T> {
+        self.values()
+    }
+}
+
+/// Returns an iterator over the distinct values in the snapshot along with their weights,
+/// consuming the snapshot.
+impl<T> IntoIterator for Snapshot<T>
+where
+    T: Value,
+{
+    type Item = (T, f64);
+    type IntoIter = IntoValues<T>;
+
+    fn into_iter(self) -> IntoValues<T> {
+        IntoValues {
+            entries: self.entries.into_iter().peekable(),
+        }
+    }
+}
+
+/// An owning iterator over the distinct values in a snapshot along with their weights.
+pub struct IntoValues<T = i64> {
+    entries: iter::Peekable<alloc::vec::IntoIter<SnapshotEntry<T>>>,
+}
+
+impl<T> Iterator for IntoValues<T>
+where
+    T: Value,
+{
+    type Item = (T, f64);
+
+    fn next(&mut self) -> Option<(T, f64)> {
+        let first = self.entries.next()?;
+        let mut weight = first.norm_weight;
+        while let Some(entry) = self.entries.next_if(|e| e.value == first.value) {
+            weight += entry.norm_weight;
+        }
+
+        Some((first.value, weight))
+    }
+}
+
+/// An iterator over the individual values in a snapshot along with their raw weights.
+pub struct RawSamples<'a, T = i64> {
+    entries: slice::Iter<'a, SnapshotEntry<T>>,
+    total_weight: f64,
+}
+
+impl<T> Iterator for RawSamples<'_, T>
+where
+    T: Value,
+{
+    type Item = (T, f64);
+
+    fn next(&mut self) -> Option<(T, f64)> {
+        self.entries
+            .next()
+            .map(|e| (e.value, e.norm_weight * self.total_weight))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for RawSamples<'_, T>
+where
+    T: Value,
+{
+    fn next_back(&mut self) -> Option<(T, f64)> {
+        self.entries
+            .next_back()
+            .map(|e| (e.value, e.norm_weight * self.total_weight))
+    }
+}
+
+impl<T> ExactSizeIterator for RawSamples<'_, T> where T: Value {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+    use std::ops::Range;
+    use std::sync::Mutex;
+
+    #[test]
+    fn a_histogram_of_100_out_of_1000_elements() {
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .size(100)
+            .alpha(0.99)
+            .build();
+        for i in 0..1000 {
+            histogram.update(i);
+        }
+
+        assert_eq!(histogram.values.len(), 100);
+
+        let snapshot = histogram.snapshot();
+
+        assert_eq!(snapshot.entries.len(), 100);
+
+        assert_all_values_between(snapshot, 0..1000);
+    }
+
+    #[test]
+    fn a_histogram_of_100_out_of_10_elements() {
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .size(100)
+            .alpha(0.99)
+            .build();
+        for i in 0..10 {
+            histogram.update(i);
+        }
+
+        let snapshot = histogram.snapshot();
+
+        assert_eq!(snapshot.entries.len(), 10);
+
+        assert_all_values_between(snapshot, 0..10);
+    }
+
+    #[test]
+    fn a_heavily_biased_histogram_of_100_out_of_1000_elements() {
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .size(1000)
+            .alpha(0.01)
+            .build();
+        for i in 0..100 {
+            histogram.update(i);
+        }
+
+        assert_eq!(histogram.values.len(), 100);
+
+        let snapshot = histogram.snapshot();
+
+        assert_eq!(snapshot.entries.len(), 100);
+
+        assert_all_values_between(snapshot, 0..100);
+    }
+
+    #[test]
+    fn long_periods_of_inactivity_should_not_corrupt_sampling_state() {
+        let mut now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(10)
+            .alpha(0.015)
+            .build();
+
+        // add 1000 values at a rate of 10 values/second
+        let delta = Duration::from_millis(100);
+        for i in 0..1000 {
+            now += delta;
+            histogram.update_at(now, 1000 + i);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.entries.len(), 10);
+        assert_all_values_between(snapshot, 1000..2000);
+
+        // wait for 15 hours and add another value.
+        // this should trigger a rescale. Note that the number of samples will
+        // be reduced because of the very small scaling factor that will make
+        // all existing priorities equal to zero after rescale.
+        now += Duration::from_secs(15 * 60 * 60);
+        histogram.update_at(now, 2000);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.entries.len(), 2);
+        assert_all_values_between(snapshot, 1000..3000);
+
+        // add 1000 values at a rate of 10 values/second
+        for i in 0..1000 {
+            now += delta;
+            histogram.update_at(now, 3000 + i);
+        }
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.entries.len(), 10);
+        assert_all_values_between(snapshot, 3000..4000);
+    }
+
+    #[test]
+    fn spot_lift() {
+        let mut now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(1000)
+            .alpha(0.015)
+            .build();
+
+        let values_per_minute = 10;
+        let values_interval = Duration::from_secs(60) / values_per_minute;
+        // mode 1: steady regime for 120 minutes
+        for _ in 0..120 * values_per_minute {
+            histogram.update_at(now, 177);
+            now += values_interval;
+        }
+
+        // switching to mode 2: 10 minutes with the same rate, but larger value
+        for _ in 0..10 * values_per_minute {
+            histogram.update_at(now, 9999);
+            now += values_interval;
+        }
+
+        // expect that the quantiles should be about mode 2 after 10 minutes
+        assert_eq!(histogram.snapshot().value(0.5), 9999);
+    }
+
+    #[test]
+    fn spot_fall() {
+        let mut now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(1000)
+            .alpha(0.015)
+            .build();
+
+        let values_per_minute = 10;
+        let values_interval = Duration::from_secs(60) / values_per_minute;
+        // mode 1: steady regime for 120 minutes
+        for _ in 0..120 * values_per_minute {
+            histogram.update_at(now, 9998);
+            now += values_interval;
+        }
+
+        // switching to mode 2: 10 minutes with the same rate, but smaller value
+        for _ in 0..10 * values_per_minute {
+            histogram.update_at(now, 178);
+            now += values_interval;
+        }
+
+        // expect that the quantiles should be about mode 2 after 10 minutes
+        assert_eq!(histogram.snapshot().value(0.5), 178);
+    }
+
+    #[test]
+    fn quantiles_should_be_based_on_weights() {
+        let mut now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(1000)
+            .alpha(0.015)
+            .build();
+
+        for _ in 0..40 {
+            histogram.update_at(now, 177);
+        }
+
+        now += Duration::from_secs(120);
+
+        for _ in 0..10 {
+            histogram.update_at(now, 9999);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.entries.len(), 50);
+
+        // the first added 40 items (177) have weights 1
+        // the next added 10 items (9999) have weights ~6
+        // so, it's 40 vs 60 distribution, not 40 vs 10
+        assert_eq!(snapshot.value(0.5), 9999);
+        assert_eq!(snapshot.value(0.75), 9999);
+    }
+
+    #[test]
+    fn merge() {
+        let now = Instant::now();
+        let mut a = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(100)
+            .build();
+        let mut b = ExponentialDecayHistogram::builder()
+            .at(now + Duration::from_secs(10))
+            .size(100)
+            .build();
+
+        for i in 0..100 {
+            a.update_at(now, i);
+            b.update_at(now + Duration::from_secs(10), 1000 + i);
+        }
+
+        a.merge(&b);
+        assert_eq!(a.values.len(), 100);
+
+        let snapshot = a.snapshot();
+        assert_eq!(snapshot.count(), 200);
+        assert!(snapshot.min() < 100);
+        assert!(snapshot.max() >= 1000);
+        assert_all_values_between(snapshot, 0..1100);
+    }
+
+    #[test]
+    fn merge_stale() {
+        let start = Instant::now();
+        let later = start + Duration::from_secs(20 * 60 * 60);
+        let histogram = |time| {
+            let mut histogram = ExponentialDecayHistogram::builder().at(time).build();
+            histogram.update_at(time, if time == start { 1 } else { 2 });
+            histogram
+        };
+
+        // merging a stale histogram into a fresh one scales its weights down to nothing
+        let mut fresh = histogram(later);
+        fresh.merge(&histogram(start));
+        let snapshot = fresh.snapshot();
+        assert_eq!(snapshot.count(), 2);
+        assert_eq!(snapshot.value(0.5), 2);
+
+        // merging a fresh histogram into a stale one moves the stale one's landmark forward
+        let mut stale = histogram(start);
+        stale.merge(&histogram(later));
+        assert_eq!(stale.start_time(), later);
+        let snapshot = stale.snapshot();
+        assert_eq!(snapshot.count(), 2);
+        assert_eq!(snapshot.value(0.5), 2);
+    }
+
+    #[test]
+    fn clear() {
+        let mut now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(100)
+            .build();
+
+        for i in 0..1000 {
+            histogram.update_at(now, 1000 + i);
+        }
+
+        now += Duration::from_secs(60);
+        histogram.clear_at(now);
+        assert_eq!(histogram.size, 100);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 0);
+        assert_eq!(snapshot.min(), 0);
+        assert_eq!(snapshot.max(), 0);
+        assert_eq!(snapshot.mean(), 0.);
+
+        for i in 0..10 {
+            histogram.update_at(now, i);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 10);
+        assert_eq!(snapshot.min(), 0);
+        assert_eq!(snapshot.max(), 9);
+        assert!((snapshot.mean() - 4.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn len() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        let snapshot = histogram.snapshot();
+        assert!(snapshot.is_empty());
+        assert_eq!(snapshot.len(), 0);
+
+        for i in 0..5 {
+            histogram.update_at(now, i);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert!(!snapshot.is_empty());
+        assert_eq!(snapshot.len(), 5);
+        assert_eq!(snapshot.count(), 5);
+
+        for i in 5..5000 {
+            histogram.update_at(now, i);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.len(), 1028);
+        assert_eq!(snapshot.count(), 5000);
+    }
+
+    #[test]
+    fn try_stats() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.try_value(0.5), None);
+        assert_eq!(snapshot.try_min(), None);
+        assert_eq!(snapshot.try_max(), None);
+        assert_eq!(snapshot.try_mean(), None);
+        assert_eq!(snapshot.value(0.5), 0);
+        assert_eq!(snapshot.min(), 0);
+        assert_eq!(snapshot.max(), 0);
+        assert_eq!(snapshot.mean(), 0.);
+
+        histogram.update_at(now, 0);
+        histogram.update_at(now, 10);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.try_value(0.), Some(0));
+        assert_eq!(snapshot.try_value(1.), Some(10));
+        assert_eq!(snapshot.try_min(), Some(0));
+        assert_eq!(snapshot.try_max(), Some(10));
+        assert_eq!(snapshot.try_mean(), Some(5.));
+    }
+
+    #[test]
+    fn quantiles() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        let quantiles = [0.99, 0.5, 0., 0.75, 1., 0.999, 0.95, 0.9];
+        assert_eq!(histogram.snapshot().quantiles(&quantiles), vec![0; 8]);
+
+        for i in 0..2000 {
+            histogram.update_at(now, i);
+        }
+
+        let snapshot = histogram.snapshot();
+        let expected = quantiles
+            .iter()
+            .map(|&q| snapshot.value(q))
+            .collect::<Vec<_>>();
+        assert_eq!(snapshot.quantiles(&quantiles), expected);
+    }
+
+    #[test]
+    fn unsigned_values() {
+        let mut histogram = ExponentialDecayHistogram::<u64>::builder().size(10).build();
+        let now = histogram.start_time;
+
+        histogram.update_at(now, u64::MAX);
+        histogram.update_at(now, u64::MAX - 1);
+        histogram.update_at(now, 0);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 3);
+        assert_eq!(snapshot.min(), 0);
+        assert_eq!(snapshot.max(), u64::MAX);
+        assert_eq!(snapshot.value(0.25), u64::MAX - 1);
+        assert_eq!(
+            snapshot.values().map(|(v, _)| v).collect::<Vec<_>>(),
+            vec![0, u64::MAX - 1, u64::MAX]
+        );
+    }
+
+    #[test]
+    fn clone() {
+        let mut histogram = ExponentialDecayHistogram::builder().size(100).build();
+        let now = histogram.start_time;
+
+        for i in 0..1000 {
+            histogram.update_at(now, i);
+        }
+
+        let mut clone = histogram.clone();
+        assert_eq!(
+            clone.snapshot().values().collect::<Vec<_>>(),
+            histogram.snapshot().values().collect::<Vec<_>>()
+        );
+
+        for i in 1000..2000 {
+            histogram.update_at(now, i);
+            clone.update_at(now, i);
+        }
+
+        assert_eq!(
+            clone.snapshot().values().collect::<Vec<_>>(),
+            histogram.snapshot().values().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn sub_second_weights() {
+        let now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder().at(now).build();
+
+        histogram.update_at(now + Duration::from_millis(100), 1);
+        histogram.update_at(now + Duration::from_millis(400), 2);
+
+        let weights = histogram
+            .values
+            .iter()
+            .map(|s| (s.value, s.weight))
+            .collect::<HashMap<_, _>>();
+        assert!(weights[&1] < weights[&2]);
+    }
+
+    #[test]
+    fn rescale_threshold() {
+        let mut now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(now)
+            .rescale_threshold(Duration::from_secs(60))
+            .build();
+
+        histogram.update_at(now, 1);
+        assert_eq!(histogram.start_time, now);
+
+        now += Duration::from_secs(30);
+        histogram.update_at(now, 2);
+        assert_ne!(histogram.start_time, now);
+
+        now += Duration::from_secs(31);
+        histogram.update_at(now, 3);
+        assert_eq!(histogram.start_time, now);
+        assert_eq!(histogram.next_scale_time, now + Duration::from_secs(60));
+    }
+
+    #[test]
+    fn half_life() {
+        let now = Instant::now();
+        let histogram = ExponentialDecayHistogram::<i64>::builder()
+            .at(now)
+            .half_life(Duration::from_secs(300))
+            .build();
+
+        let old_weight = histogram.weight(now);
+        let new_weight = histogram.weight(now + Duration::from_secs(300));
+        assert!((old_weight / new_weight - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn accessors() {
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .size(10)
+            .alpha(0.5)
+            .build();
+        assert_eq!(histogram.size(), 10);
+        assert_eq!(histogram.alpha(), 0.5);
+        assert_eq!(histogram.count(), 0);
+
+        for i in 0..100 {
+            histogram.update(i);
+        }
+        assert_eq!(histogram.count(), 100);
+    }
+
+    #[test]
+    fn sum() {
+        let now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(now)
+            .half_life(Duration::from_secs(1))
+            .build();
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.weighted_sum(), 0.);
+        assert_eq!(snapshot.sample_sum(), 0.);
+
+        // the second value has twice the weight of the first
+        histogram.update_at(now, 10);
+        histogram.update_at(now + Duration::from_secs(1), 20);
+
+        let snapshot = histogram.snapshot();
+        assert!((snapshot.weighted_sum() - 2. * 50. / 3.).abs() < 1e-9);
+        assert_eq!(snapshot.sample_sum(), 30.);
+    }
+
+    #[test]
+    fn extend() {
+        let mut manual = ExponentialDecayHistogram::new();
+        for i in 0..1000 {
+            manual.update(i);
+        }
+
+        let mut extended = ExponentialDecayHistogram::new();
+        extended.extend(0..1000);
+        assert_eq!(extended.count(), manual.count());
+
+        let collected = (0..1000).collect::<ExponentialDecayHistogram>();
+        assert_eq!(collected.count(), manual.count());
+        assert_all_values_between(collected.snapshot(), 0..1000);
+    }
+
+    #[test]
+    fn rank() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().rank(0), 0.);
+
+        for i in 1..=4 {
+            histogram.update_at(now, i * 100);
+        }
+        histogram.update_at(now, 400);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.rank(0), 0.);
+        assert!((snapshot.rank(100) - 0.2).abs() < 1e-9);
+        assert!((snapshot.rank(150) - 0.2).abs() < 1e-9);
+        assert!((snapshot.rank(300) - 0.6).abs() < 1e-9);
+        assert_eq!(snapshot.rank(400), 1.);
+        assert_eq!(snapshot.rank(1000), 1.);
+    }
+
+    #[test]
+    fn interpolated_value() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().interpolated_value(0.5), 0.);
+
+        histogram.update_at(now, 100);
+        histogram.update_at(now, 200);
+
+        let snapshot = histogram.snapshot();
+        assert!((snapshot.interpolated_value(0.5) - 150.).abs() < 1e-9);
+        assert!((snapshot.interpolated_value(0.375) - 125.).abs() < 1e-9);
+        assert_eq!(snapshot.interpolated_value(0.), 100.);
+        assert_eq!(snapshot.interpolated_value(0.1), 100.);
+        assert_eq!(snapshot.interpolated_value(1.), 200.);
+    }
+
+    #[test]
+    fn interpolated_value_with_underflowed_weights() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(1.).build();
+        let now = histogram.start_time;
+
+        // the rescale underflows the weight of the first value to 0
+        histogram.update_at(now, 1);
+        histogram.update_at(now + Duration::from_secs(2 * 60 * 60), 3);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.interpolated_value(0.), 3.);
+        assert_eq!(snapshot.interpolated_value(0.1), 3.);
+        assert_eq!(snapshot.interpolated_value(1.), 3.);
+    }
+
+    #[test]
+    fn interpolated_value_with_equal_positions() {
+        let entry = |value, norm_weight: f64, quantile| SnapshotEntry {
+            value,
+            norm_weight,
+            quantile: NotNan::new(quantile).unwrap(),
+        };
+        // the positions of the middle two values both round to 0.5
+        let snapshot = Snapshot {
+            entries: vec![
+                entry(1, 0.5, 0.),
+                entry(2, 1e-17, 0.5),
+                entry(3, 1e-17, 0.5),
+                entry(4, 0.5, 0.5),
+            ],
+            count: 4,
+            mean: 2.5,
+            total_weight: 1.,
+        };
+
+        assert_eq!(snapshot.interpolated_value(0.5), 3.);
+        for i in 0..=100 {
+            let value = snapshot.interpolated_value(i as f64 / 100.);
+            assert!((1. ..=4.).contains(&value));
+        }
+    }
+
+    #[test]
+    fn try_update_at() {
+        let now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(now + Duration::from_secs(10))
+            .build();
+
+        assert_eq!(
+            histogram.try_update_at(now, 1),
+            Err(UpdateError::NonMonotonicTime)
+        );
+        assert_eq!(histogram.count(), 0);
+
+        histogram
+            .try_update_at(now + Duration::from_secs(10), 2)
+            .unwrap();
+        assert_eq!(histogram.count(), 1);
+    }
+
+    #[test]
+    fn far_future_instant() {
+        let mut now = Instant::now();
+        let mut step = Duration::MAX;
+        while step > Duration::from_secs(1) {
+            match now.checked_add(step) {
+                Some(time) => now = time,
+                None => step /= 2,
+            }
+        }
+
+        let mut histogram = ExponentialDecayHistogram::builder().at(now).build();
+        histogram.update_at(now, 1);
+        histogram.clear_at(now);
+        histogram.rescale(now);
+        histogram.update_at(now, 2);
+
+        assert_eq!(histogram.snapshot().value(0.5), 2);
+    }
+
+    #[derive(Debug, Clone)]
+    struct MockClock(Arc<Mutex<Instant>>);
+
+    impl MockClock {
+        fn new() -> MockClock {
+            MockClock(Arc::new(Mutex::new(Instant::now())))
+        }
+
+        fn advance(&self, duration: Duration) {
+            *self.0.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[test]
+    fn mock_clock() {
+        let clock = MockClock::new();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .clock(clock.clone())
+            .build();
+        assert_eq!(histogram.start_time, clock.now());
+
+        for _ in 0..1200 {
+            histogram.update(177);
+            clock.advance(Duration::from_secs(6));
+        }
+
+        for _ in 0..100 {
+            histogram.update(9999);
+            clock.advance(Duration::from_secs(6));
+        }
+
+        assert_eq!(histogram.snapshot().value(0.5), 9999);
+    }
+
+    #[test]
+    fn seed() {
+        let now = Instant::now();
+        let mut a = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(100)
+            .seed(42)
+            .build();
+        let mut b = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(100)
+            .seed(42)
+            .build();
+
+        for i in 0..1000 {
+            let time = now + Duration::from_millis(i as u64 * 10);
+            a.update_at(time, i);
+            b.update_at(time, i);
+        }
+
+        let a = a.snapshot();
+        let b = b.snapshot();
+        assert_eq!(a.count(), b.count());
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.entries.iter().zip(&b.entries) {
+            assert_eq!(a.value, b.value);
+            assert_eq!(a.norm_weight.to_bits(), b.norm_weight.to_bits());
+            assert_eq!(a.quantile, b.quantile);
+        }
+    }
+
+    #[test]
+    fn values_rev() {
+        let now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder().at(now).build();
+
+        for i in 0..100 {
+            histogram.update_at(now + Duration::from_secs(i), i as i64 % 7);
+        }
+
+        let snapshot = histogram.snapshot();
+        let mut forward = snapshot.values().collect::<Vec<_>>();
+        forward.reverse();
+        let backward = snapshot.values().rev().collect::<Vec<_>>();
+        assert_eq!(forward, backward);
+
+        let mut values = snapshot.values();
+        assert_eq!(values.next().map(|v| v.0), Some(0));
+        assert_eq!(values.next_back().map(|v| v.0), Some(6));
+        assert_eq!(values.map(|v| v.0).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn summary() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(
+            snapshot.summary(),
+            Summary {
+                count: 0,
+                min: 0,
+                max: 0,
+                mean: 0.,
+                stddev: 0.,
+                median: 0,
+            }
+        );
+
+        for i in 0..100 {
+            histogram.update_at(now + Duration::from_secs(i), i as i64);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(
+            snapshot.summary(),
+            Summary {
+                count: snapshot.count(),
+                min: snapshot.min(),
+                max: snapshot.max(),
+                mean: snapshot.mean(),
+                stddev: snapshot.stddev(),
+                median: snapshot.value(0.5),
+            }
+        );
+    }
+
+    #[test]
+    fn rate() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(0.).build();
+        let now = histogram.start_time;
+
+        for i in 0..100 {
+            histogram.update_at(now, i);
+        }
+        let previous = histogram.snapshot();
+
+        histogram.clear_at(now);
+        for i in 0..100 {
+            histogram.update_at(now, i + 50);
+        }
+        let current = histogram.snapshot();
+
+        let elapsed = Duration::from_secs(10);
+        assert_eq!(current.value(0.5) - previous.value(0.5), 50);
+        assert_eq!(current.rate(&previous, elapsed, 0.5), 5.);
+        assert_eq!(previous.rate(&current, elapsed, 0.5), -5.);
+        assert_eq!(current.rate(&current, elapsed, 0.99), 0.);
+    }
+
+    #[test]
+    fn summary_stats() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+        for i in 0..100 {
+            histogram.update_at(now, i);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(
+            snapshot.summary_stats(&[0.5, 0.99]),
+            SummaryStats {
+                count: 100,
+                min: 0,
+                max: 99,
+                mean: snapshot.mean(),
+                stddev: snapshot.stddev(),
+                quantiles: vec![(0.5, snapshot.value(0.5)), (0.99, snapshot.value(0.99))],
+            }
+        );
+    }
+
+    #[test]
+    fn update_many() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        histogram.update_many_at(now, 5, 0);
+        assert_eq!(histogram.count(), 0);
+        assert!(histogram.snapshot().is_empty());
+
+        histogram.update_many_at(now, 5, 1000);
+        histogram.update_at(now, 10);
+
+        let mut manual = ExponentialDecayHistogram::new();
+        for _ in 0..1000 {
+            manual.update_at(now, 5);
+        }
+        manual.update_at(now, 10);
+
+        assert_eq!(histogram.count(), manual.count());
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        let values = snapshot.values().collect::<Vec<_>>();
+        assert_eq!(values[0].0, 5);
+        assert!((values[0].1 - 1000. / 1001.).abs() < 1e-9);
+        assert!((snapshot.mean() - manual.snapshot().mean()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn durations() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        histogram.update_duration_at(now, Duration::from_millis(10));
+        histogram.update_duration_at(now, Duration::from_millis(20));
+        histogram.update_duration_at(now, Duration::from_millis(30));
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.min_duration(), Duration::from_millis(10));
+        assert_eq!(snapshot.max_duration(), Duration::from_millis(30));
+        assert_eq!(snapshot.value_duration(1.), Duration::from_millis(30));
+        assert_eq!(snapshot.mean_duration(), Duration::from_millis(20));
+
+        histogram.update_duration_at(now, Duration::MAX);
+        assert_eq!(histogram.snapshot().max(), i64::MAX);
+    }
+
+    #[test]
+    fn total_weight() {
+        let mut now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder().at(now).build();
+
+        assert_eq!(histogram.snapshot().total_weight(), 0.);
+
+        for i in 0..100 {
+            histogram.update_at(now, i);
+            now += Duration::from_secs(1);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert!(snapshot.total_weight() > 0.);
+        let normalized = snapshot.values().map(|v| v.1).sum::<f64>();
+        assert!((normalized - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn snapshot_into() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        let mut snapshot = Snapshot::default();
+        assert!(snapshot.is_empty());
+
+        for i in 0..100 {
+            histogram.update_at(now, i);
+        }
+        histogram.snapshot_into(&mut snapshot);
+        assert_eq!(snapshot.count(), 100);
+        assert_eq!(snapshot.len(), 100);
+
+        histogram.clear_at(now);
+        histogram.update_at(now, 1000);
+        histogram.snapshot_into(&mut snapshot);
+        assert_eq!(snapshot.count(), 1);
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot.mean(), 1000.);
+        assert_eq!(snapshot.total_weight(), 1.);
+    }
+
+    #[test]
+    fn trimmed_mean() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().trimmed_mean(0.05, 0.95), 0.);
+
+        for i in 1..=99 {
+            histogram.update_at(now, i);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert!((snapshot.trimmed_mean(0.05, 0.95) - 50.).abs() < 1e-6);
+        assert!((snapshot.trimmed_mean(0., 1.) - snapshot.mean()).abs() < 1e-6);
+
+        histogram.update_at(now, 1_000_000);
+
+        let snapshot = histogram.snapshot();
+        assert!(snapshot.mean() > 10_000.);
+        assert!((snapshot.trimmed_mean(0.05, 0.95) - 50.).abs() < 1.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn trimmed_mean_invalid_bounds() {
+        ExponentialDecayHistogram::<i64>::new()
+            .snapshot()
+            .trimmed_mean(0.6, 0.4);
+    }
+
+    #[test]
+    fn winsorized_mean() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(0.).build();
+        let now = histogram.start_time;
+        assert_eq!(histogram.snapshot().winsorized_mean(0.15, 0.85), 0.);
+
+        for i in 1..=8 {
+            histogram.update_at(now, i);
+        }
+        histogram.update_at(now, -1_000);
+        histogram.update_at(now, 1_000);
+
+        let snapshot = histogram.snapshot();
+        assert!((snapshot.winsorized_mean(0., 1.) - snapshot.mean()).abs() < 1e-6);
+
+        // the outliers are clamped to 1 and 8 rather than being discarded
+        let winsorized = snapshot.winsorized_mean(0.05, 0.75);
+        assert!((winsorized - 4.5).abs() < 1e-6);
+        let trimmed = snapshot.trimmed_mean(0.1, 0.9);
+        assert!((trimmed - 4.5).abs() < 1e-6);
+
+        histogram.update_at(now, 1_000);
+        let snapshot = histogram.snapshot();
+        // with an asymmetric tail, the clamped values pull the mean towards the upper bound
+        let winsorized = snapshot.winsorized_mean(0.15, 0.85);
+        let trimmed = snapshot.trimmed_mean(0.15, 0.85);
+        assert!(winsorized > trimmed);
+        assert!(winsorized < 1_000.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn winsorized_mean_invalid_bounds() {
+        ExponentialDecayHistogram::<i64>::new()
+            .snapshot()
+            .winsorized_mean(0.5, 0.5);
+    }
+
+    #[test]
+    fn skewness_and_kurtosis() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.skewness(), 0.);
+        assert_eq!(snapshot.kurtosis(), 0.);
+
+        histogram.update_at(now, 5);
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.skewness(), 0.);
+        assert_eq!(snapshot.kurtosis(), 0.);
+
+        let mut histogram = ExponentialDecayHistogram::new();
+        for i in 1..=100 {
+            histogram.update_at(now, i);
+        }
+
+        // a discrete uniform distribution is symmetric with an excess kurtosis of about -1.2
+        let snapshot = histogram.snapshot();
+        assert!(snapshot.skewness().abs() < 1e-6);
+        assert!((snapshot.kurtosis() + 1.2).abs() < 0.01);
+
+        let mut histogram = ExponentialDecayHistogram::new();
+        for _ in 0..9 {
+            histogram.update_at(now, 0);
+        }
+        histogram.update_at(now, 10);
+
+        // a Bernoulli distribution with p = 0.1
+        let snapshot = histogram.snapshot();
+        let expected_skewness = 0.8 / (0.09f64).sqrt();
+        let expected_kurtosis = (1. - 6. * 0.09) / 0.09;
+        assert!((snapshot.skewness() - expected_skewness).abs() < 1e-6);
+        assert!((snapshot.kurtosis() - expected_kurtosis).abs() < 1e-6);
+    }
+
+    #[test]
+    fn mad() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().mad(), 0.);
+
+        histogram.update_at(now, 3);
+        assert_eq!(histogram.snapshot().mad(), 0.);
+
+        for &value in &[1, 2, 10] {
+            histogram.update_at(now, value);
+        }
+
+        // the median is 3, so the deviations are 0, 1, 2, and 7
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.value(0.5), 3);
+        assert_eq!(snapshot.mad(), 2.);
+
+        histogram.update_at(now, 1_000_000);
+        assert!(histogram.snapshot().mad() < 10.);
+    }
+
+    #[test]
+    fn display() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().to_string(), "count=0 (empty)");
+
+        for &value in &[1, 2, 3, 10] {
+            histogram.update_at(now, value);
+        }
+
+        assert_eq!(
+            histogram.snapshot().to_string(),
+            "count=4 min=1 max=10 mean=4.00 stddev=3.54 median=3",
+        );
+    }
+
+    #[test]
+    fn effective_sample_size() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(1.).build();
+        let mut now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().effective_sample_size(), 0.);
+
+        for i in 0..10 {
+            histogram.update_at(now, i);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert!((snapshot.effective_sample_size() - 10.).abs() < 1e-6);
+
+        for i in 10..20 {
+            now += Duration::from_secs(1);
+            histogram.update_at(now, i);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.len(), 20);
+        assert!(snapshot.effective_sample_size() < 5.);
+    }
+
+    #[test]
+    fn cdf() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().cdf(0), 0.);
+
+        for &value in &[10, 20, 20, 30] {
+            histogram.update_at(now, value);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.cdf(i64::MIN), 0.);
+        assert_eq!(snapshot.cdf(9), 0.);
+        assert!((snapshot.cdf(10) - 0.25).abs() < 1e-9);
+        assert!((snapshot.cdf(15) - 0.25).abs() < 1e-9);
+        assert!((snapshot.cdf(20) - 0.75).abs() < 1e-9);
+        assert!((snapshot.cdf(29) - 0.75).abs() < 1e-9);
+        assert_eq!(snapshot.cdf(30), 1.);
+        assert_eq!(snapshot.cdf(i64::MAX), 1.);
+    }
+
+    #[test]
+    fn snapshot_and_reset() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        for i in 0..10 {
+            histogram.update(i);
+        }
+
+        let snapshot = histogram.snapshot_and_reset();
+        assert_eq!(snapshot.count(), 10);
+        assert_eq!(snapshot.len(), 10);
+        assert_eq!(snapshot.min(), 0);
+        assert_eq!(snapshot.max(), 9);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 0);
+        assert!(snapshot.is_empty());
+
+        histogram.update(20);
+        assert_eq!(histogram.snapshot_and_reset().max(), 20);
+    }
+
+    #[test]
+    fn range() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().range(), 0);
+
+        histogram.update_at(now, -5);
+        assert_eq!(histogram.snapshot().range(), 0);
+
+        histogram.update_at(now, 10);
+        assert_eq!(histogram.snapshot().range(), 15);
+
+        let mut histogram = ExponentialDecayHistogram::new();
+        histogram.update_at(now, i64::MIN + 1);
+        histogram.update_at(now, i64::MAX - 1);
+        assert_eq!(histogram.snapshot().range(), i64::MAX);
+
+        let mut histogram = ExponentialDecayHistogram::new();
+        histogram.update_at(now, i64::MAX - 10);
+        histogram.update_at(now, i64::MAX);
+        assert_eq!(histogram.snapshot().range(), 10);
+    }
+
+    #[test]
+    fn logical_clock() {
+        #[derive(Debug)]
+        struct PanicClock;
+
+        impl Clock for PanicClock {
+            fn now(&self) -> Instant {
+                panic!("clock consulted")
+            }
+        }
+
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(Instant::now())
+            .clock(PanicClock)
+            .logical_clock(true)
+            .size(10)
+            .alpha(1.)
+            .rescale_threshold(Duration::from_secs(100))
+            .build();
+
+        for i in 0..1000 {
+            histogram.update(i);
+        }
+        histogram.update_many(1000, 2);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 1002);
+        assert_eq!(snapshot.max(), 1000);
+        assert!(snapshot.min() > 950);
+
+        histogram.clear();
+        assert!(histogram.snapshot().is_empty());
+    }
+
+    #[test]
+    fn rescale_times() {
+        let start = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(start)
+            .rescale_threshold(Duration::from_secs(60))
+            .build();
+
+        assert_eq!(histogram.start_time(), start);
+        assert_eq!(
+            histogram.next_rescale_time(),
+            start + Duration::from_secs(60)
+        );
+
+        histogram.update_at(start + Duration::from_secs(59), 1);
+        assert_eq!(histogram.start_time(), start);
+
+        let now = start + Duration::from_secs(90);
+        histogram.update_at(now, 2);
+        assert_eq!(histogram.start_time(), now);
+        assert_eq!(histogram.next_rescale_time(), now + Duration::from_secs(60));
+    }
+
+    #[test]
+    fn manual_rescale() {
+        let clock = MockClock::new();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .clock(clock.clone())
+            .build();
+
+        for i in 0..100 {
+            histogram.update(i);
+            clock.advance(Duration::from_millis(100));
+        }
+        let now = clock.now();
+
+        let before = histogram.snapshot();
+        histogram.rescale_at(now);
+        let after = histogram.snapshot();
+
+        assert_eq!(histogram.start_time(), now);
+        assert_eq!(histogram.next_rescale_time(), now + RESCALE_THRESHOLD);
+        assert_eq!(after.count(), before.count());
+        assert!((after.mean() - before.mean()).abs() < 1e-9);
+        assert!((after.stddev() - before.stddev()).abs() < 1e-9);
+        for ((a, a_weight), (b, b_weight)) in after.values().zip(before.values()) {
+            assert_eq!(a, b);
+            assert!((a_weight - b_weight).abs() < 1e-9);
+        }
+
+        clock.advance(Duration::from_secs(1));
+        histogram.rescale_now();
+        assert_eq!(histogram.start_time(), clock.now());
+    }
+
+    #[test]
+    fn iqr() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.iqr(), 0);
+        assert_eq!(snapshot.outlier_bounds(), (0., 0.));
+
+        for i in 1..=8 {
+            histogram.update_at(now, i);
+        }
+
+        // the quartiles are 3 and 7
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.iqr(), 4);
+        assert_eq!(snapshot.outlier_bounds(), (-3., 13.));
+
+        let mut histogram = ExponentialDecayHistogram::new();
+        for &value in &[i64::MIN, i64::MIN, i64::MAX, i64::MAX] {
+            histogram.update_at(now, value);
+        }
+        assert_eq!(histogram.snapshot().iqr(), i64::MAX);
+    }
+
+    #[test]
+    fn geometric_and_harmonic_means() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.geometric_mean(), 0.);
+        assert_eq!(snapshot.harmonic_mean(), 0.);
+
+        for &value in &[1, 2, 4, 8] {
+            histogram.update_at(now, value);
+        }
+
+        // (1 * 2 * 4 * 8)^(1/4) = 64^(1/4)
+        // 4 / (1 + 1/2 + 1/4 + 1/8) = 32/15
+        let snapshot = histogram.snapshot();
+        assert!((snapshot.geometric_mean() - 64f64.powf(0.25)).abs() < 1e-9);
+        assert!((snapshot.harmonic_mean() - 32. / 15.).abs() < 1e-9);
+
+        histogram.update_at(now, 0);
+        histogram.update_at(now, -5);
+
+        let snapshot = histogram.snapshot();
+        assert!((snapshot.geometric_mean() - 64f64.powf(0.25)).abs() < 1e-9);
+        assert!((snapshot.harmonic_mean() - 32. / 15.).abs() < 1e-9);
+
+        let mut histogram = ExponentialDecayHistogram::new();
+        histogram.update_at(now, -1);
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.geometric_mean(), 0.);
+        assert_eq!(snapshot.harmonic_mean(), 0.);
+    }
+
+    #[test]
+    fn mode() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().mode(), None);
+
+        for &value in &[5, 1, 3] {
+            histogram.update_at(now, value);
+        }
+        assert_eq!(histogram.snapshot().mode(), Some(1));
+
+        for _ in 0..10 {
+            histogram.update_at(now, 3);
+        }
+        assert_eq!(histogram.snapshot().mode(), Some(3));
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        for &value in &[1, 2, 2, 3, 3, 3] {
+            histogram.update_at(now, value);
+        }
+
+        let snapshot = histogram.snapshot();
+        let borrowed = (&snapshot).into_iter().collect::<Vec<_>>();
+        assert_eq!(borrowed, snapshot.values().collect::<Vec<_>>());
+
+        let owned = snapshot.into_iter().collect::<Vec<_>>();
+        assert_eq!(owned, borrowed);
+        assert_eq!(
+            owned.iter().map(|&(value, _)| value).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn cdf_points() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let mut now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().cdf_points(), vec![]);
+
+        for i in 0..100 {
+            histogram.update_at(now, i % 10);
+            now += Duration::from_secs(1);
+        }
+
+        let snapshot = histogram.snapshot();
+        let points = snapshot.cdf_points();
+        assert_eq!(points.len(), 10);
+        assert_eq!(points.last().unwrap().1, 1.);
+        for pair in points.windows(2) {
+            assert!(pair[0].0 < pair[1].0);
+            assert!(pair[0].1 <= pair[1].1);
+        }
+        for &(value, cdf) in &points {
+            assert_eq!(cdf, snapshot.cdf(value));
+        }
+    }
+
+    #[test]
+    fn snapshot_eq() {
+        let now = Instant::now();
+        let mut builder = ExponentialDecayHistogram::builder();
+        builder.at(now).size(10).seed(7);
+
+        let mut a = builder.build();
+        let mut b = builder.build();
+        for i in 0..100 {
+            let time = now + Duration::from_secs(i);
+            a.update_at(time, i as i64);
+            b.update_at(time, i as i64);
+        }
+
+        assert_eq!(a.snapshot(), b.snapshot());
+
+        b.update_at(now + Duration::from_secs(100), 100);
+        assert_ne!(a.snapshot(), b.snapshot());
+    }
+
+    #[test]
+    fn estimated_memory_bytes() {
+        let mut histogram = ExponentialDecayHistogram::builder().size(1000).build();
+        let now = histogram.start_time;
+
+        let empty = histogram.estimated_memory_bytes();
+        assert_eq!(empty, mem::size_of::<ExponentialDecayHistogram>());
+
+        let sample_size = mem::size_of::<WeightedSample<i64>>();
+        let mut last = empty;
+        for i in 0..2000 {
+            histogram.update_at(now, i);
+
+            let estimate = histogram.estimated_memory_bytes();
+            assert!(estimate >= last);
+            assert!(estimate >= empty + histogram.values.len() * sample_size);
+            assert!(estimate <= empty + 2 * histogram.values.len().max(4) * sample_size);
+            last = estimate;
+        }
+
+        let mut histogram = ExponentialDecayHistogram::builder().size(1000).build();
+        histogram.reserve();
+        let reserved = histogram.estimated_memory_bytes();
+        assert_eq!(reserved, empty + 1000 * sample_size);
+
+        for i in 0..2000 {
+            histogram.update_at(now, i);
+            assert_eq!(histogram.estimated_memory_bytes(), reserved);
+        }
+    }
+
+    #[test]
+    fn value_boundaries() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        for &value in &[10, 20, 30, 40] {
+            histogram.update_at(now, value);
+        }
+
+        // the values' ranges start at 0, 0.25, 0.5, and 0.75
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.value(0.), 10);
+        assert_eq!(snapshot.value(0.2), 20);
+        assert_eq!(snapshot.value(0.25), 20);
+        assert_eq!(snapshot.value(0.3), 30);
+        assert_eq!(snapshot.value(0.5), 30);
+        assert_eq!(snapshot.value(0.99), 40);
+        assert_eq!(snapshot.value(1.), 40);
+        assert_eq!(
+            snapshot.quantiles(&[1., 0.5, 0.25, 0.2, 0.]),
+            vec![40, 30, 20, 20, 10]
+        );
+
+        for i in 1..=7 {
+            histogram.update_at(now, i);
+        }
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.value(0.), 1);
+        assert_eq!(snapshot.value(1.), 40);
+    }
+
+    #[test]
+    fn value_boundaries_with_underflowed_weights() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(1.).build();
+        let now = histogram.start_time;
+
+        // the rescale underflows the weight of the first value to 0
+        histogram.update_at(now, 1);
+        histogram.update_at(now + Duration::from_secs(2 * 60 * 60), 3);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.value(0.), 1);
+        assert_eq!(snapshot.value(0.5), 3);
+        assert_eq!(snapshot.value(1.), 3);
+        assert_eq!(snapshot.quantiles(&[0., 0.5, 1.]), vec![1, 3, 3]);
+    }
+
+    #[test]
+    fn snapshot_after_long_manual_rescale() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(1.).build();
+        let start = histogram.start_time;
+
+        histogram.update_at(start, 1);
+        histogram.rescale_at(start + Duration::from_secs(2 * 60 * 60));
+        assert_eq!(histogram.reservoir_len(), 1);
+
+        let snapshot = histogram.snapshot();
+        assert!(snapshot.is_empty());
+        assert_eq!(snapshot.count(), 1);
+        assert_eq!(snapshot.total_weight(), 0.);
+        assert_eq!(snapshot.mean(), 0.);
+        assert_eq!(snapshot.value(0.5), 0);
+
+        // the next insert has a nonzero weight again
+        histogram.update_at(start + Duration::from_secs(2 * 60 * 60), 2);
+        assert_eq!(histogram.snapshot().value(0.5), 2);
+    }
+
+    #[test]
+    fn update_weighted() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        for i in 0..10 {
+            histogram.update_at(now, i);
+        }
+        histogram.update_weighted_at(now, 100, 90.);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 11);
+        assert_eq!(snapshot.value(0.5), 100);
+        assert!((snapshot.rank(9) - 0.1).abs() < 1e-9);
+
+        histogram.update_weighted(5, 0.5);
+        assert_eq!(histogram.count(), 12);
+    }
+
+    #[test]
+    #[should_panic]
+    fn update_weighted_nan() {
+        ExponentialDecayHistogram::new().update_weighted(1, f64::NAN);
+    }
+
+    #[test]
+    #[should_panic]
+    fn update_weighted_negative() {
+        ExponentialDecayHistogram::new().update_weighted(1, -1.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn nan_alpha() {
+        ExponentialDecayHistogram::<i64>::builder()
+            .alpha(f64::NAN)
+            .build();
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_alpha() {
+        ExponentialDecayHistogram::<i64>::builder()
+            .alpha(-0.015)
+            .build();
+    }
+
+    #[test]
+    #[should_panic]
+    fn infinite_alpha() {
+        ExponentialDecayHistogram::<i64>::builder()
+            .alpha(f64::INFINITY)
+            .build();
+    }
+
+    #[test]
+    fn zero_alpha() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(0.).build();
+        let mut now = histogram.start_time;
+
+        for i in 0..10 {
+            histogram.update_at(now, i);
+            now += Duration::from_secs(60);
+        }
+
+        for (_, weight) in histogram.snapshot().values() {
+            assert!((weight - 0.1).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn stddev_with() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.stddev_with(Bias::Population), 0.);
+        assert_eq!(snapshot.stddev_with(Bias::Sample), 0.);
+
+        for &value in &[2, 4, 4, 4, 5, 5, 7, 9] {
+            histogram.update_at(now, value);
+        }
+
+        // the mean is 5 and the sum of squared deviations is 32
+        let snapshot = histogram.snapshot();
+        assert!((snapshot.stddev_with(Bias::Population) - 2.).abs() < 1e-9);
+        assert!((snapshot.stddev_with(Bias::Sample) - (32f64 / 7.).sqrt()).abs() < 1e-9);
+        assert_eq!(snapshot.stddev(), snapshot.stddev_with(Bias::Population));
+    }
+
+    #[test]
+    fn to_buckets() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(histogram.snapshot().to_buckets(4), vec![]);
+
+        histogram.update_at(now, 7);
+        assert_eq!(histogram.snapshot().to_buckets(4), vec![(7, 7, 1.)]);
+
+        for i in 0..100 {
+            histogram.update_at(now, i);
+        }
+
+        let buckets = histogram.snapshot().to_buckets(4);
+        let bounds = buckets
+            .iter()
+            .map(|&(low, high, _)| (low, high))
+            .collect::<Vec<_>>();
+        assert_eq!(bounds, vec![(0, 24), (25, 49), (50, 74), (75, 99)]);
+        // 7 was recorded twice
+        let weights = buckets.iter().map(|b| b.2 * 101.).collect::<Vec<_>>();
+        for (weight, expected) in weights.iter().zip(&[26., 25., 25., 25.]) {
+            assert!((weight - expected).abs() < 1e-9);
+        }
+
+        let mut histogram = ExponentialDecayHistogram::new();
+        histogram.update_at(now, i64::MIN);
+        histogram.update_at(now, i64::MAX);
+        let buckets = histogram.snapshot().to_buckets(2);
+        assert_eq!(buckets, vec![(i64::MIN, -1, 0.5), (0, i64::MAX, 0.5)]);
+        let total = buckets.iter().map(|b| b.2).sum::<f64>();
+        assert!((total - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn normal_quantile() {
+        assert!(super::normal_quantile(0.5).abs() < 1e-9);
+        assert!((super::normal_quantile(0.975) - 1.959964).abs() < 1e-6);
+        assert!((super::normal_quantile(0.005) + 2.575829).abs() < 1e-6);
+    }
+
+    #[test]
+    fn quantile_confidence_interval() {
+        let now = Instant::now();
+        let mut small = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(100)
+            .build();
+        let mut large = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(1000)
+            .build();
+
+        assert_eq!(
+            small.snapshot().quantile_confidence_interval(0.5, 0.95),
+            (0, 0)
+        );
+
+        for i in 0..10_000 {
+            small.update_at(now, i);
+            large.update_at(now, i);
+        }
+
+        let small = small.snapshot();
+        let large = large.snapshot();
+
+        let (small_low, small_high) = small.quantile_confidence_interval(0.5, 0.95);
+        let (large_low, large_high) = large.quantile_confidence_interval(0.5, 0.95);
+        assert!(small_low <= small.value(0.5) && small.value(0.5) <= small_high);
+        assert!(large_low <= large.value(0.5) && large.value(0.5) <= large_high);
+        assert!(large_high - large_low < small_high - small_low);
+
+        let (low, high) = large.quantile_confidence_interval(0.5, 0.5);
+        assert!(large_low <= low && high <= large_high);
+    }
+
+    #[test]
+    fn from_values() {
+        let values = [3, 1, 4, 1, 5, 9, 2, 6];
+
+        let histogram = ExponentialDecayHistogram::from(&values[..]);
+        assert_eq!(histogram.count(), 8);
+        assert_eq!(histogram.snapshot().max(), 9);
+
+        let histogram = ExponentialDecayHistogram::from(values.to_vec());
+        assert_eq!(histogram.count(), 8);
+        assert_eq!(histogram.snapshot().min(), 1);
+    }
+
+    #[test]
+    fn ranks() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let mut now = histogram.start_time;
+
+        let values = [500, -1, 2000, 100, 0, 999, 100, 200];
+        assert_eq!(histogram.snapshot().ranks(&values), vec![0.; 8]);
+
+        for i in 0..1000 {
+            histogram.update_at(now, i);
+            now += Duration::from_millis(100);
+        }
+
+        let snapshot = histogram.snapshot();
+        let expected = values.iter().map(|&v| snapshot.rank(v)).collect::<Vec<_>>();
+        assert_eq!(snapshot.ranks(&values), expected);
+        assert_eq!(snapshot.ranks(&[-1, 2000]), vec![0., 1.]);
+    }
+
+    #[test]
+    fn set_size() {
+        let mut histogram = ExponentialDecayHistogram::builder().size(100).build();
+        let mut now = histogram.start_time;
+
+        for i in 0..1000 {
+            histogram.update_at(now, i);
+            now += Duration::from_millis(100);
+        }
+        assert_eq!(histogram.values.len(), 100);
+
+        let min_priority = histogram.values.iter().map(|s| s.priority).min().unwrap();
+        histogram.set_size(10);
+        assert_eq!(histogram.size(), 10);
+        assert_eq!(histogram.values.len(), 10);
+        // the retained values are the highest priority ones
+        assert!(histogram.values.iter().all(|s| s.priority > min_priority));
+
+        histogram.set_size(50);
+        assert_eq!(histogram.values.len(), 10);
+        for i in 0..1000 {
+            histogram.update_at(now, i);
+            now += Duration::from_millis(100);
+        }
+        assert_eq!(histogram.values.len(), 50);
+        assert_eq!(histogram.count(), 2000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_size_zero() {
+        ExponentialDecayHistogram::<i64>::new().set_size(0);
+    }
+
+    #[test]
+    fn set_alpha() {
+        let start = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(start)
+            .alpha(0.)
+            .build();
+
+        histogram.update_at(start, 0);
+        histogram.update_at(start + Duration::from_secs(10), 0);
+
+        // a half-life of 10 seconds
+        histogram.set_alpha_at(
+            start + Duration::from_secs(10),
+            std::f64::consts::LN_2 / 10.,
+        );
+        assert_eq!(histogram.alpha(), std::f64::consts::LN_2 / 10.);
+        assert_eq!(histogram.start_time(), start + Duration::from_secs(10));
+
+        // the old values were recorded without decay, so they're still weighted equally, and the
+        // new value is worth twice each of them
+        histogram.update_at(start + Duration::from_secs(20), 1);
+        let values = histogram.snapshot().values().collect::<Vec<_>>();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].0, 0);
+        assert!((values[0].1 - 0.5).abs() < 1e-9);
+        assert_eq!(values[1].0, 1);
+        assert!((values[1].1 - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn set_alpha_after_long_inactivity() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(1.).build();
+        let start = histogram.start_time;
+
+        // the rescale underflows the weight of the existing value to 0
+        histogram.update_at(start, 1);
+        histogram.set_alpha_at(start + Duration::from_secs(2 * 60 * 60), 0.5);
+
+        let snapshot = histogram.snapshot();
+        assert!(snapshot.is_empty());
+        assert_eq!(snapshot.count(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_alpha_nan() {
+        ExponentialDecayHistogram::<i64>::new().set_alpha(f64::NAN);
+    }
+
+    #[test]
+    fn raw_samples() {
+        let mut histogram = ExponentialDecayHistogram::builder().size(100).build();
+        let mut now = histogram.start_time;
+
+        for i in 0..1000 {
+            histogram.update_at(now, i % 10);
+            now += Duration::from_millis(100);
+        }
+
+        let snapshot = histogram.snapshot();
+        let samples = snapshot.raw_samples().collect::<Vec<_>>();
+        assert_eq!(samples.len(), snapshot.len());
+        assert_eq!(snapshot.raw_samples().len(), snapshot.len());
+        assert!(samples.windows(2).all(|w| w[0].0 <= w[1].0));
+
+        let mut expected = histogram
+            .values
+            .iter()
+            .map(|s| (s.value, s.weight))
+            .collect::<Vec<_>>();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut actual = samples.clone();
+        actual.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for ((ev, ew), (av, aw)) in expected.iter().zip(&actual) {
+            assert_eq!(ev, av);
+            assert!((ew - aw).abs() <= ew * 1e-9);
+        }
+
+        let total = samples.iter().map(|(_, w)| w).sum::<f64>();
+        assert!((total - snapshot.total_weight()).abs() <= total * 1e-9);
+    }
+
+    #[test]
+    fn builder_samples() {
+        let mut now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(100)
+            .build();
+
+        for i in 0..1000 {
+            histogram.update_at(now, i);
+            now += Duration::from_millis(100);
+        }
+
+        let snapshot = histogram.snapshot();
+        let restored = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(100)
+            .samples(snapshot.raw_samples())
+            .build();
+        assert_eq!(restored.count(), 100);
+
+        let actual = restored.snapshot();
+        assert_eq!(actual.min(), snapshot.min());
+        assert_eq!(actual.max(), snapshot.max());
+        assert_eq!(actual.value(0.5), snapshot.value(0.5));
+        assert!((actual.mean() - snapshot.mean()).abs() < 1e-6);
+        assert!((actual.stddev() - snapshot.stddev()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn builder_samples_oversized() {
+        let histogram = ExponentialDecayHistogram::builder()
+            .size(2)
+            .samples(vec![(1, 1.), (2, 3.), (3, 2.), (4, 0.5)])
+            .build();
+        assert_eq!(histogram.count(), 4);
+
+        assert_eq!(histogram.evicted_count(), 2);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(
+            snapshot.values().map(|(v, _)| v).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+    }
+
+    #[test]
+    fn count_saturates() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        histogram.update_many(0, u64::MAX - 1);
+        assert_eq!(histogram.count(), u64::MAX - 1);
+
+        histogram.update(1);
+        assert_eq!(histogram.count(), u64::MAX);
+        histogram.update_many(2, 10);
+        assert_eq!(histogram.count(), u64::MAX);
+        histogram.update_weighted(3, 2.);
+        assert_eq!(histogram.count(), u64::MAX);
+
+        let other = histogram.clone();
+        histogram.merge(&other);
+        assert_eq!(histogram.count(), u64::MAX);
+    }
+
+    #[test]
+    fn quantile_is_reliable() {
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .size(10_000)
+            .alpha(0.)
+            .build();
+        let now = histogram.start_time;
+
+        let snapshot = histogram.snapshot();
+        assert!(!snapshot.quantile_is_reliable(0.5));
+
+        for i in 0..20 {
+            histogram.update_at(now, i);
+        }
+        let snapshot = histogram.snapshot();
+        assert!(snapshot.quantile_is_reliable(0.5));
+        assert!(!snapshot.quantile_is_reliable(0.99));
+        assert!(!snapshot.quantile_is_reliable(0.999));
+        assert!(!snapshot.quantile_is_reliable(0.01));
+
+        for i in 0..9980 {
+            histogram.update_at(now, i);
+        }
+        let snapshot = histogram.snapshot();
+        assert!(snapshot.quantile_is_reliable(0.99));
+        assert!(snapshot.quantile_is_reliable(0.999));
+        assert!(!snapshot.quantile_is_reliable(0.9999));
+        assert!(!snapshot.quantile_is_reliable(1.));
+    }
+
+    #[test]
+    fn diff() {
+        let mut baseline = ExponentialDecayHistogram::builder().alpha(0.).build();
+        let mut current = ExponentialDecayHistogram::builder().alpha(0.).build();
+        let now = baseline.start_time;
+
+        for i in 1..=100 {
+            baseline.update_at(now, i);
+            current.update_at(now, i * 2);
+        }
+        current.update_at(now, 202);
+
+        let baseline = baseline.snapshot();
+        let current = current.snapshot();
+        let diff = current.diff(&baseline, &[0.5, 0.99]);
+
+        assert_eq!(diff.count, 1);
+        assert_eq!(diff.mean, current.mean() - baseline.mean());
+        assert_eq!(diff.stddev, current.stddev() - baseline.stddev());
+        assert_eq!(
+            diff.quantiles,
+            vec![
+                (0.5, (current.value(0.5) - baseline.value(0.5)) as f64),
+                (0.99, (current.value(0.99) - baseline.value(0.99)) as f64),
+            ]
+        );
+        assert!(diff.quantiles.iter().all(|&(_, d)| d > 0.));
+
+        let reverse = baseline.diff(&current, &[0.5, 0.99]);
+        assert_eq!(reverse.count, -1);
+        assert_eq!(reverse.mean, -diff.mean);
+        assert_eq!(reverse.quantiles[0].1, -diff.quantiles[0].1);
+    }
+
+    #[test]
+    fn time_until_rescale() {
+        let start = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(start)
+            .rescale_threshold(Duration::from_secs(60))
+            .build();
+
+        assert_eq!(histogram.time_until_rescale(start), Duration::from_secs(60));
+        assert_eq!(
+            histogram.time_until_rescale(start + Duration::from_secs(45)),
+            Duration::from_secs(15)
+        );
+        assert_eq!(
+            histogram.time_until_rescale(start + Duration::from_secs(90)),
+            Duration::from_secs(0)
+        );
+
+        histogram.update_at(start + Duration::from_secs(90), 1);
+        assert_eq!(
+            histogram.time_until_rescale(start + Duration::from_secs(90)),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn f64_values() {
+        let mut histogram = ExponentialDecayHistogramF64::builder().alpha(0.).build();
+        let now = histogram.start_time;
+
+        for i in 0..100 {
+            histogram.update_f64_at(now, i as f64 + 0.5);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(*snapshot.min(), 0.5);
+        assert_eq!(*snapshot.max(), 99.5);
+        assert_eq!(*snapshot.value(0.495), 50.5);
+        assert!((snapshot.mean() - 50.).abs() < 1e-9);
+        assert_eq!(
+            snapshot
+                .quantiles(&[0.245, 0.745])
+                .into_iter()
+                .map(|v| *v)
+                .collect::<Vec<_>>(),
+            vec![25.5, 75.5]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn f64_nan() {
+        ExponentialDecayHistogramF64::new().update_f64(f64::NAN);
+    }
+
+    #[test]
+    fn value_table() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+        for i in 0..1000 {
+            histogram.update_at(now, i);
+        }
+
+        let snapshot = histogram.snapshot();
+        let table = snapshot.value_table(0.25);
+        assert_eq!(
+            table.iter().map(|&(q, _)| q).collect::<Vec<_>>(),
+            vec![0., 0.25, 0.5, 0.75, 1.]
+        );
+        assert!(table.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(table[0].1, snapshot.min());
+        assert_eq!(table[4].1, snapshot.max());
+
+        assert_eq!(snapshot.value_table(0.1).len(), 11);
+        assert_eq!(
+            snapshot
+                .value_table(0.3)
+                .iter()
+                .map(|&(q, _)| q)
+                .collect::<Vec<_>>(),
+            vec![0., 0.3, 0.6, 0.8999999999999999, 1.]
+        );
+        assert_eq!(snapshot.value_table(1.).len(), 2);
+    }
+
+    #[test]
+    fn variance() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let mut now = histogram.start_time;
+        assert_eq!(histogram.snapshot().variance(), 0.);
+
+        for i in 0..1000 {
+            histogram.update_at(now, i * i % 997);
+            now += Duration::from_millis(100);
+        }
+
+        let snapshot = histogram.snapshot();
+        let variance = snapshot.variance();
+        assert!(variance > 0.);
+        assert!((snapshot.stddev().powi(2) - variance).abs() <= variance * 1e-12);
+        assert!(
+            (snapshot.stddev_with(Bias::Sample).powi(2) - snapshot.variance_with(Bias::Sample))
+                .abs()
+                <= variance * 1e-12
+        );
+        assert!(snapshot.variance_with(Bias::Sample) > variance);
+    }
+
+    #[test]
+    fn coefficient_of_variation() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+        assert_eq!(histogram.snapshot().coefficient_of_variation(), 0.);
+
+        for _ in 0..50 {
+            histogram.update_at(now, 5);
+            histogram.update_at(now, 15);
+        }
+
+        // a mean of 10 and a standard deviation of 5
+        let snapshot = histogram.snapshot();
+        assert!((snapshot.coefficient_of_variation() - 0.5).abs() < 1e-9);
+
+        let mut histogram = ExponentialDecayHistogram::new();
+        histogram.update_at(now, -1);
+        histogram.update_at(now, 1);
+        assert_eq!(histogram.snapshot().coefficient_of_variation(), 0.);
     }
 
     #[test]
-    fn a_histogram_of_100_out_of_10_elements() {
+    fn no_rescale() {
+        let start = Instant::now();
         let mut histogram = ExponentialDecayHistogram::builder()
-            .size(100)
-            .alpha(0.99)
+            .at(start)
+            .no_rescale()
             .build();
+
+        let mut now = start;
         for i in 0..10 {
-            histogram.update(i);
+            histogram.update_at(now, i);
+            now += Duration::from_secs(60 * 60);
         }
 
+        assert_eq!(histogram.start_time(), start);
+        assert!(histogram.next_rescale_time() > now);
+        assert_eq!(histogram.snapshot().len(), 10);
+    }
+
+    #[test]
+    fn no_rescale_overflow() {
+        let start = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(start)
+            .size(10)
+            .alpha(1.)
+            .no_rescale()
+            .build();
+
+        histogram.update_at(start + Duration::from_secs(650), 1);
+        assert_eq!(histogram.start_time(), start);
+
+        // e^680 is finite, but could overflow when summed or converted to a priority
+        histogram.update_at(start + Duration::from_secs(680), 2);
+        assert_eq!(histogram.start_time(), start + Duration::from_secs(680));
+
+        let values = histogram.snapshot().values().collect::<Vec<_>>();
+        assert_eq!(values.len(), 2);
+        assert!((values[0].1 / values[1].1 / (-30f64).exp() - 1.).abs() < 1e-9);
+
+        // the weight of the first value underflows to 0
+        histogram.update_at(start + Duration::from_secs(2000), 3);
         let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.value(0.5), 3);
+        assert_eq!(snapshot.max(), 3);
+    }
 
-        assert_eq!(snapshot.entries.len(), 10);
+    #[test]
+    fn update_weighted_after_long_gap() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let start = histogram.start_time;
 
-        assert_all_values_between(snapshot, 0..10);
+        histogram.update_at(start, 1);
+        histogram.update_weighted_at(start + Duration::from_secs(3000), 2, 1e280);
+        assert_eq!(histogram.start_time(), start + Duration::from_secs(3000));
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.value(0.5), 2);
+        assert!(snapshot.total_weight().is_finite());
     }
 
     #[test]
-    fn a_heavily_biased_histogram_of_100_out_of_1000_elements() {
-        let mut histogram = ExponentialDecayHistogram::builder()
-            .size(1000)
-            .alpha(0.01)
-            .build();
+    #[should_panic(expected = "weight is too large")]
+    fn update_weighted_too_large() {
+        ExponentialDecayHistogram::new().update_weighted(1, 1e300);
+    }
+
+    #[test]
+    fn evicted_count() {
+        let mut histogram = ExponentialDecayHistogram::builder().size(10).build();
+        let mut now = histogram.start_time;
+
         for i in 0..100 {
-            histogram.update(i);
+            histogram.update_at(now, i);
+            now += Duration::from_millis(100);
+            assert_eq!(
+                histogram.evicted_count(),
+                histogram.count().saturating_sub(10)
+            );
         }
 
-        assert_eq!(histogram.values.len(), 100);
+        histogram.set_size(5);
+        assert_eq!(histogram.evicted_count(), 95);
+
+        histogram.clear();
+        assert_eq!(histogram.evicted_count(), 0);
+    }
+
+    #[test]
+    fn approx_eq() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+        for i in 0..10 {
+            histogram.update_at(now, i);
+        }
 
         let snapshot = histogram.snapshot();
+        let mut other = histogram.snapshot();
+        assert!(snapshot.approx_eq(&other, 0.));
 
-        assert_eq!(snapshot.entries.len(), 100);
+        for entry in &mut other.entries {
+            entry.norm_weight *= 1. + 1e-12;
+        }
+        assert!(snapshot != other);
+        assert!(!snapshot.approx_eq(&other, 0.));
+        assert!(snapshot.approx_eq(&other, 1e-9));
 
-        assert_all_values_between(snapshot, 0..100);
+        other.entries[0].value = 100;
+        assert!(!snapshot.approx_eq(&other, 1e-9));
+
+        histogram.update_at(now, 10);
+        assert!(!snapshot.approx_eq(&histogram.snapshot(), 1.));
     }
 
     #[test]
-    fn long_periods_of_inactivity_should_not_corrupt_sampling_state() {
-        let mut now = Instant::now();
+    fn presets() {
+        let histogram = Builder::<i64>::monitoring_defaults().build();
+        assert_eq!(histogram.size(), 1028);
+        assert_eq!(histogram.alpha(), 0.015);
+
+        let histogram = Builder::<i64>::high_accuracy().build();
+        assert_eq!(histogram.size(), 4112);
+        assert_eq!(histogram.alpha(), 0.015);
+
+        let histogram = Builder::<i64>::fast_decay().size(10).build();
+        assert_eq!(histogram.size(), 10);
+        assert_eq!(histogram.alpha(), 0.1);
+    }
+
+    #[test]
+    fn try_build() {
+        assert!(ExponentialDecayHistogram::<i64>::builder()
+            .try_build()
+            .is_ok());
+
+        let builder = || ExponentialDecayHistogram::<i64>::builder();
+        assert_eq!(
+            builder().size(0).try_build().unwrap_err(),
+            BuilderError::ZeroSize
+        );
+        assert_eq!(
+            builder().alpha(f64::NAN).try_build().unwrap_err(),
+            BuilderError::InvalidAlpha
+        );
+        assert_eq!(
+            builder().alpha(-1.).try_build().unwrap_err(),
+            BuilderError::InvalidAlpha
+        );
+        assert_eq!(
+            builder()
+                .half_life(Duration::from_secs(0))
+                .try_build()
+                .unwrap_err(),
+            BuilderError::InvalidAlpha
+        );
+        assert_eq!(
+            builder()
+                .rescale_threshold(Duration::from_secs(0))
+                .try_build()
+                .unwrap_err(),
+            BuilderError::ZeroRescaleThreshold
+        );
+        assert_eq!(
+            builder()
+                .samples(vec![(1, 1.), (2, 0.)])
+                .try_build()
+                .unwrap_err(),
+            BuilderError::InvalidSampleWeight
+        );
+        assert_eq!(
+            builder().samples(vec![(1, 1e300)]).try_build().unwrap_err(),
+            BuilderError::InvalidSampleWeight
+        );
+        assert_eq!(
+            builder()
+                .rescale_threshold(Duration::from_secs(60))
+                .start_elapsed(Duration::from_secs(61))
+                .try_build()
+                .unwrap_err(),
+            BuilderError::InvalidStartElapsed
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "histogram size must be positive")]
+    fn build_zero_size() {
+        ExponentialDecayHistogram::<i64>::builder().size(0).build();
+    }
+
+    #[test]
+    fn signed_extremes() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(0.).build();
+        let now = histogram.start_time;
+
+        let values = [
+            i64::MAX,
+            -1_000_000_000_000,
+            i64::MIN,
+            -1,
+            0,
+            1,
+            1_000_000_000_000,
+            i64::MIN + 1,
+            i64::MAX - 1,
+        ];
+        for &value in &values {
+            histogram.update_at(now, value);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.min(), i64::MIN);
+        assert_eq!(snapshot.max(), i64::MAX);
+        assert_eq!(snapshot.value(0.4), 0);
+        assert_eq!(snapshot.value(0.), i64::MIN);
+        assert_eq!(snapshot.value(1.), i64::MAX);
+
+        let mut sorted = values;
+        sorted.sort();
+        assert_eq!(
+            snapshot.values().map(|(v, _)| v).collect::<Vec<_>>(),
+            sorted
+        );
+        assert_eq!(
+            snapshot.quantiles(&[0., 0.1, 0.95]),
+            vec![i64::MIN, i64::MIN + 1, i64::MAX]
+        );
+
+        assert_eq!(snapshot.range(), i64::MAX);
+        assert_eq!(snapshot.rank(i64::MIN), snapshot.cdf(i64::MIN));
+        assert!(snapshot.cdf(i64::MIN) > 0.);
+        assert_eq!(snapshot.cdf(i64::MAX), 1.);
+        assert_eq!(snapshot.cdf(-2), 3. / 9.);
+        assert!(snapshot.mean().abs() < 1e4);
+        assert_eq!(snapshot.to_buckets(2)[0].0, i64::MIN);
+        assert_eq!(snapshot.to_buckets(2)[1].1, i64::MAX);
+    }
+
+    #[test]
+    fn merge_all() {
+        assert!(ExponentialDecayHistogram::<i64>::merge_all(vec![]).is_none());
+
+        let start = Instant::now();
+        let histograms = (0..3)
+            .map(|i| {
+                let mut histogram = ExponentialDecayHistogram::builder()
+                    .at(start)
+                    .size(1000)
+                    .build();
+                for j in 0..100 {
+                    histogram.update_at(start + Duration::from_secs(j), i * 100 + j as i64);
+                }
+                histogram
+            })
+            .collect::<Vec<_>>();
+
+        let merged = ExponentialDecayHistogram::merge_all(histograms).unwrap();
+        assert_eq!(merged.count(), 300);
+        assert_eq!(merged.size(), 1000);
+        assert_eq!(merged.start_time(), start);
+
+        let snapshot = merged.snapshot();
+        assert_eq!(snapshot.len(), 300);
+        assert_eq!(snapshot.min(), 0);
+        assert_eq!(snapshot.max(), 299);
+    }
+
+    #[test]
+    fn merge_all_stale_first() {
+        let start = Instant::now();
+        let histograms = (0..3)
+            .map(|i| {
+                let time = start + Duration::from_secs(i * 20 * 60 * 60);
+                let mut histogram = ExponentialDecayHistogram::builder().at(time).build();
+                histogram.update_at(time, i as i64);
+                histogram
+            })
+            .collect::<Vec<_>>();
+
+        let merged = ExponentialDecayHistogram::merge_all(histograms).unwrap();
+        assert_eq!(
+            merged.start_time(),
+            start + Duration::from_secs(40 * 60 * 60)
+        );
+
+        let snapshot = merged.snapshot();
+        assert_eq!(snapshot.count(), 3);
+        assert_eq!(snapshot.value(0.5), 2);
+    }
+
+    #[test]
+    fn nearest() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+        assert_eq!(histogram.snapshot().nearest(0), None);
+
+        for &value in &[10, 20, 20, 50] {
+            histogram.update_at(now, value);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.nearest(i64::MIN), Some(10));
+        assert_eq!(snapshot.nearest(14), Some(10));
+        assert_eq!(snapshot.nearest(15), Some(10));
+        assert_eq!(snapshot.nearest(16), Some(20));
+        assert_eq!(snapshot.nearest(20), Some(20));
+        assert_eq!(snapshot.nearest(36), Some(50));
+        assert_eq!(snapshot.nearest(i64::MAX), Some(50));
+
+        histogram.update_at(now, i64::MIN);
+        histogram.update_at(now, i64::MAX);
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.nearest(i64::MIN + 1), Some(i64::MIN));
+        assert_eq!(snapshot.nearest(i64::MAX - 1), Some(i64::MAX));
+        assert_eq!(snapshot.nearest(0), Some(10));
+    }
+
+    #[test]
+    fn colliding_priorities() {
+        let start = Instant::now();
         let mut histogram = ExponentialDecayHistogram::builder()
-            .at(now)
-            .size(10)
-            .alpha(0.015)
+            .at(start)
+            .alpha(1.)
+            .seed(1)
             .build();
 
-        // add 1000 values at a rate of 10 values/second
-        let delta = Duration::from_millis(100);
+        // this value's priority will underflow when the histogram is rescaled
+        histogram.update_weighted_at(start, 1, 1e-310);
+        histogram.update_at(start, 2);
+        histogram.update_at(start, 3);
+
+        // merging the histogram with a copy of itself duplicates every priority
+        let other = histogram.clone();
+        histogram.merge(&other);
+        let mut priorities = histogram
+            .values
+            .iter()
+            .map(|s| s.priority)
+            .collect::<Vec<_>>();
+        priorities.sort();
+        priorities.dedup();
+        assert_eq!(priorities.len(), 3);
+        assert_eq!(histogram.snapshot().len(), 6);
+
+        histogram.rescale_at(start + Duration::from_secs(60));
+        assert_eq!(
+            histogram.snapshot().values().collect::<Vec<_>>(),
+            vec![(1, 0.), (2, 0.5), (3, 0.5)]
+        );
+        assert_eq!(histogram.snapshot().len(), 5);
+        assert_eq!(histogram.evicted_count(), 1);
+    }
+
+    #[test]
+    fn mean_now() {
+        let mut histogram = ExponentialDecayHistogram::builder().size(100).build();
+        let mut now = histogram.start_time;
+        assert_eq!(histogram.mean_now(), 0.);
+
         for i in 0..1000 {
-            now += delta;
-            histogram.update_at(now, 1000 + i);
+            histogram.update_at(now, i);
+            now += Duration::from_millis(100);
         }
 
-        let snapshot = histogram.snapshot();
-        assert_eq!(snapshot.entries.len(), 10);
-        assert_all_values_between(snapshot, 1000..2000);
+        let mean = histogram.snapshot().mean();
+        assert!((histogram.mean_now() - mean).abs() <= mean * 1e-12);
 
-        // wait for 15 hours and add another value.
-        // this should trigger a rescale. Note that the number of samples will
-        // be reduced because of the very small scaling factor that will make
-        // all existing priorities equal to zero after rescale.
-        now += Duration::from_secs(15 * 60 * 60);
-        histogram.update_at(now, 2000);
+        let value = 30_000_000_007i64;
+        let mut histogram = ExponentialDecayHistogram::builder().size(100).build();
+        for _ in 0..1000 {
+            histogram.update_at(now, value);
+            now += Duration::from_millis(731);
+        }
+        assert_eq!(histogram.mean_now(), value as f64);
+        assert_eq!(histogram.mean_now(), histogram.snapshot().mean());
+    }
+
+    #[test]
+    fn custom_rng() {
+        // a xorshift generator
+        struct TestRng(u64);
+
+        impl rand::RngCore for TestRng {
+            fn next_u32(&mut self) -> u32 {
+                self.next_u64() as u32
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                self.0 ^= self.0 << 13;
+                self.0 ^= self.0 >> 7;
+                self.0 ^= self.0 << 17;
+                self.0
+            }
+
+            fn fill_bytes(&mut self, dst: &mut [u8]) {
+                rand::rand_core::impls::fill_bytes_via_next(self, dst)
+            }
+        }
+
+        let start = Instant::now();
+        let build = || {
+            let mut histogram = ExponentialDecayHistogram::builder()
+                .at(start)
+                .size(10)
+                .build_with_rng(TestRng(1));
+            for i in 0..100 {
+                histogram.update_at(start + Duration::from_secs(i), i as i64);
+            }
+            histogram
+        };
+
+        let a = build();
+        let b = build();
+        assert_eq!(a.snapshot(), b.snapshot());
+        assert_eq!(a.snapshot().len(), 10);
+
+        let mut merged = ExponentialDecayHistogram::builder().at(start).build();
+        merged.merge(&a);
+        assert_eq!(merged.snapshot(), a.snapshot());
+    }
+
+    #[test]
+    fn to_log_buckets() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+        assert_eq!(histogram.snapshot().to_log_buckets(10., 1), vec![]);
+
+        for &value in &[-5, 0, 1, 10, 100, 1_000, 10_000, 100_000, 1_000_000] {
+            histogram.update_at(now, value);
+        }
 
         let snapshot = histogram.snapshot();
-        assert_eq!(snapshot.entries.len(), 2);
-        assert_all_values_between(snapshot, 1000..3000);
+        let buckets = snapshot.to_log_buckets(10., 1);
+        assert_eq!(
+            buckets.iter().map(|b| (b.0, b.1)).collect::<Vec<_>>(),
+            vec![
+                (i64::MIN, 0),
+                (1, 9),
+                (10, 99),
+                (100, 999),
+                (1_000, 9_999),
+                (10_000, 99_999),
+                (100_000, 999_999),
+                (1_000_000, 9_999_999),
+            ]
+        );
+        assert!((buckets[0].2 - 2. / 9.).abs() < 1e-9);
+        assert!(buckets[1..].iter().all(|b| (b.2 - 1. / 9.).abs() < 1e-9));
 
-        // add 1000 values at a rate of 10 values/second
-        for i in 0..1000 {
-            now += delta;
-            histogram.update_at(now, 3000 + i);
+        // narrow buckets are at least one integer wide
+        let buckets = snapshot.to_log_buckets(1.5, 1);
+        assert_eq!(buckets[1], (1, 1, 1. / 9.));
+        assert_eq!((buckets[2].0, buckets[2].1), (2, 2));
+        assert!(buckets.windows(2).all(|w| w[0].1 + 1 == w[1].0));
+
+        let buckets = snapshot.to_log_buckets(2., 1_000);
+        assert_eq!(buckets[0].1, 999);
+        assert!((buckets[0].2 - 5. / 9.).abs() < 1e-9);
+        assert_eq!((buckets[1].0, buckets[1].1), (1_000, 1_999));
+    }
+
+    #[test]
+    fn to_log_buckets_limits() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+        for &value in &[1, 1_000_000_000, i64::MAX - 1, i64::MAX] {
+            histogram.update_at(now, value);
         }
         let snapshot = histogram.snapshot();
-        assert_eq!(snapshot.entries.len(), 10);
-        assert_all_values_between(snapshot, 3000..4000);
+
+        for &(base, min_value) in &[(2., 1), (1.5, i64::MAX - 10), (2., i64::MAX)] {
+            let buckets = snapshot.to_log_buckets(base, min_value);
+            assert_eq!(buckets.last().unwrap().1, i64::MAX);
+            assert!(buckets.windows(2).all(|w| w[0].1 + 1 == w[1].0));
+            assert!((buckets.iter().map(|b| b.2).sum::<f64>() - 1.).abs() < 1e-9);
+        }
+
+        // a base close to 1 would otherwise produce a bucket per integer up to the largest value
+        let buckets = snapshot.to_log_buckets(1. + 1e-9, 1);
+        assert_eq!(buckets.len(), 1024);
+        assert_eq!(buckets.last().unwrap(), &(1023, i64::MAX, 0.75));
     }
 
     #[test]
-    fn spot_lift() {
-        let mut now = Instant::now();
+    fn reservoir_len() {
+        let mut histogram = ExponentialDecayHistogram::builder().size(10).build();
+        let now = histogram.start_time;
+        assert_eq!(histogram.reservoir_len(), 0);
+
+        for i in 0..100 {
+            histogram.update_at(now, i);
+            assert_eq!(histogram.reservoir_len(), (i as usize + 1).min(10));
+        }
+
+        assert_eq!(histogram.snapshot().len(), histogram.reservoir_len());
+    }
+
+    #[test]
+    fn filter() {
         let mut histogram = ExponentialDecayHistogram::builder()
-            .at(now)
-            .size(1000)
-            .alpha(0.015)
+            .filter(|v| v >= 0)
             .build();
+        let now = histogram.start_time;
 
-        let values_per_minute = 10;
-        let values_interval = Duration::from_secs(60) / values_per_minute;
-        // mode 1: steady regime for 120 minutes
-        for _ in 0..120 * values_per_minute {
-            histogram.update_at(now, 177);
-            now += values_interval;
+        for i in -50..50 {
+            histogram.update_at(now, i);
         }
+        histogram.update_many_at(now, -1, 10);
+        histogram.update_weighted_at(now, -1, 2.);
 
-        // switching to mode 2: 10 minutes with the same rate, but larger value
-        for _ in 0..10 * values_per_minute {
-            histogram.update_at(now, 9999);
-            now += values_interval;
-        }
+        assert_eq!(histogram.count(), 50);
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.len(), 50);
+        assert_eq!(snapshot.min(), 0);
+        assert_eq!(snapshot.max(), 49);
 
-        // expect that the quantiles should be about mode 2 after 10 minutes
-        assert_eq!(histogram.snapshot().value(0.5), 9999);
+        // clones share the filter
+        let mut clone = histogram.clone();
+        clone.update_at(now, -1);
+        assert_eq!(clone.count(), 50);
     }
 
     #[test]
-    fn spot_fall() {
-        let mut now = Instant::now();
+    fn stable_mean() {
         let mut histogram = ExponentialDecayHistogram::builder()
-            .at(now)
-            .size(1000)
-            .alpha(0.015)
+            .size(100_000)
+            .alpha(0.)
             .build();
+        let now = histogram.start_time;
 
-        let values_per_minute = 10;
-        let values_interval = Duration::from_secs(60) / values_per_minute;
-        // mode 1: steady regime for 120 minutes
-        for _ in 0..120 * values_per_minute {
-            histogram.update_at(now, 9998);
-            now += values_interval;
+        let mut sum = 0i128;
+        for i in 0..100_000i64 {
+            let value = 30_000_000_000 + i * 7919 % 1000;
+            histogram.update_at(now, value);
+            sum += value as i128;
         }
 
-        // switching to mode 2: 10 minutes with the same rate, but smaller value
-        for _ in 0..10 * values_per_minute {
-            histogram.update_at(now, 178);
-            now += values_interval;
+        let snapshot = histogram.snapshot();
+        let expected = sum as f64 / 100_000.;
+        assert!((snapshot.mean() - expected).abs() <= expected * 4. * f64::EPSILON);
+
+        let mut squares = 0i128;
+        for i in 0..100_000i64 {
+            let diff = (i * 7919 % 1000) as i128 * 100_000 - (sum - 3_000_000_000_000_000);
+            squares += diff * diff;
         }
+        let expected = squares as f64 / 1e15;
+        assert!((snapshot.variance() - expected).abs() <= expected * 1e-9);
+    }
 
-        // expect that the quantiles should be about mode 2 after 10 minutes
-        assert_eq!(histogram.snapshot().value(0.5), 178);
+    #[test]
+    fn proportion_in_range() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(0.).build();
+        let now = histogram.start_time;
+        assert_eq!(histogram.snapshot().proportion_in_range(0, 10), 0.);
+
+        for i in 0..1000 {
+            histogram.update_at(now, i);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert!((snapshot.proportion_in_range(100, 500) - 0.4).abs() < 1e-9);
+        assert!((snapshot.proportion_in_range(0, 1000) - 1.).abs() < 1e-9);
+        assert_eq!(snapshot.proportion_in_range(i64::MIN, i64::MAX), 1.);
+        assert_eq!(snapshot.proportion_in_range(500, 500), 0.);
+        assert_eq!(snapshot.proportion_in_range(2000, 3000), 0.);
+        assert!(
+            (snapshot.proportion_in_range(0, 100) + snapshot.proportion_in_range(100, 1000) - 1.)
+                .abs()
+                < 1e-9
+        );
     }
 
     #[test]
-    fn quantiles_should_be_based_on_weights() {
-        let mut now = Instant::now();
+    #[should_panic]
+    fn proportion_in_range_inverted() {
+        ExponentialDecayHistogram::new()
+            .snapshot()
+            .proportion_in_range(10, 0);
+    }
+
+    #[test]
+    fn update_elapsed() {
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(0.01).build();
+        let epoch = histogram.epoch();
+        assert_eq!(histogram.instant_at(Duration::from_secs(0)), epoch);
+
+        histogram.update_elapsed(Duration::from_secs(0), 1);
+        histogram.update_elapsed(Duration::from_secs(100), 2);
+
+        // a value 100 seconds newer has e^(0.01 * 100) times the weight
+        let weights = histogram.snapshot().values().collect::<Vec<_>>();
+        assert_eq!(weights[0].0, 1);
+        assert!((weights[1].1 / weights[0].1 - 1f64.exp()).abs() < 1e-9);
+
+        // the epoch is unaffected by rescales
+        histogram.update_elapsed(Duration::from_secs(2 * 60 * 60), 3);
+        assert_eq!(histogram.epoch(), epoch);
+        assert_eq!(
+            histogram.start_time(),
+            histogram.instant_at(Duration::from_secs(2 * 60 * 60))
+        );
+        assert_eq!(histogram.snapshot().max(), 3);
+    }
+
+    #[test]
+    fn start_elapsed() {
+        let now = Instant::now() + Duration::from_secs(60 * 60);
         let mut histogram = ExponentialDecayHistogram::builder()
             .at(now)
-            .size(1000)
-            .alpha(0.015)
+            .alpha(0.01)
+            .start_elapsed(Duration::from_secs(100))
             .build();
+        assert_eq!(histogram.start_time(), now - Duration::from_secs(100));
+        assert_eq!(
+            histogram.next_rescale_time(),
+            now + Duration::from_secs(60 * 60 - 100)
+        );
 
-        for _ in 0..40 {
-            histogram.update_at(now, 177);
-        }
-
-        now += Duration::from_secs(120);
+        // the value is 100 seconds past the landmark, so its weight is e^(0.01 * 100)
+        histogram.update_at(now, 1);
+        let (_, weight) = histogram.snapshot().raw_samples().next().unwrap();
+        assert!((weight - 1f64.exp()).abs() < 1e-9);
+    }
 
-        for _ in 0..10 {
-            histogram.update_at(now, 9999);
+    #[test]
+    fn identical_values() {
+        let value = 30_000_000_007;
+        let mut histogram = ExponentialDecayHistogram::builder().size(100).build();
+        let mut now = histogram.start_time;
+        for _ in 0..1000 {
+            histogram.update_at(now, value);
+            now += Duration::from_millis(731);
         }
 
         let snapshot = histogram.snapshot();
-        assert_eq!(snapshot.entries.len(), 50);
+        assert_eq!(snapshot.len(), 100);
+        assert_eq!(snapshot.min(), value);
+        assert_eq!(snapshot.max(), value);
+        assert_eq!(snapshot.range(), 0);
+        assert_eq!(snapshot.iqr(), 0);
+        assert_eq!(snapshot.mode(), Some(value));
+        for (_, v) in snapshot.value_table(0.01) {
+            assert_eq!(v, value);
+        }
+        assert_eq!(snapshot.interpolated_value(0.3), value as f64);
 
-        // the first added 40 items (177) have weights 1
-        // the next added 10 items (9999) have weights ~6
-        // so, it's 40 vs 60 distribution, not 40 vs 10
-        assert_eq!(snapshot.value(0.5), 9999);
-        assert_eq!(snapshot.value(0.75), 9999);
+        assert_eq!(snapshot.mean(), value as f64);
+        assert_eq!(snapshot.winsorized_mean(0.1, 0.9), value as f64);
+        assert_eq!(snapshot.trimmed_mean(0.1, 0.9), value as f64);
+        assert!((snapshot.geometric_mean() / value as f64 - 1.).abs() < 1e-12);
+        assert!((snapshot.harmonic_mean() / value as f64 - 1.).abs() < 1e-12);
+        assert_eq!(snapshot.variance(), 0.);
+        assert_eq!(snapshot.stddev(), 0.);
+        assert_eq!(snapshot.stddev_with(Bias::Sample), 0.);
+        assert_eq!(snapshot.coefficient_of_variation(), 0.);
+        assert_eq!(snapshot.skewness(), 0.);
+        assert_eq!(snapshot.kurtosis(), 0.);
+        assert_eq!(snapshot.mad(), 0.);
+        assert_eq!(snapshot.outlier_bounds(), (value as f64, value as f64));
+
+        assert_eq!(snapshot.cdf(value - 1), 0.);
+        assert_eq!(snapshot.cdf(value), 1.);
+        assert!((snapshot.proportion_in_range(value, value + 1) - 1.).abs() < 1e-9);
+
+        let buckets = snapshot.to_buckets(10);
+        assert_eq!(buckets.len(), 1);
+        assert_eq!((buckets[0].0, buckets[0].1), (value, value));
+        assert!((buckets[0].2 - 1.).abs() < 1e-9);
+
+        let buckets = snapshot.to_log_buckets(10., 1);
+        assert_eq!(buckets.iter().filter(|b| b.2 > 0.).count(), 1);
     }
 
     fn assert_all_values_between(snapshot: Snapshot, range: Range<i64>) {
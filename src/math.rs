@@ -0,0 +1,47 @@
+// Floating point functions are provided by the standard library, so without it we fall back to
+// libm's implementations.
+pub(crate) trait FloatExt {
+    fn exp(self) -> Self;
+
+    fn ln(self) -> Self;
+
+    fn sqrt(self) -> Self;
+
+    fn powf(self, n: Self) -> Self;
+
+    fn powi(self, n: i32) -> Self;
+
+    fn round(self) -> Self;
+
+    fn ceil(self) -> Self;
+}
+
+impl FloatExt for f64 {
+    fn exp(self) -> f64 {
+        libm::exp(self)
+    }
+
+    fn ln(self) -> f64 {
+        libm::log(self)
+    }
+
+    fn sqrt(self) -> f64 {
+        libm::sqrt(self)
+    }
+
+    fn powf(self, n: f64) -> f64 {
+        libm::pow(self, n)
+    }
+
+    fn powi(self, n: i32) -> f64 {
+        libm::pow(self, f64::from(n))
+    }
+
+    fn round(self) -> f64 {
+        libm::round(self)
+    }
+
+    fn ceil(self) -> f64 {
+        libm::ceil(self)
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{Builder, BuilderError, ExponentialDecayHistogram, NotNan, Snapshot, SyncHistogram};
+use metrics::{
+    Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Records values from the `metrics` crate's `histogram!` macro into the histogram.
+///
+/// NaN values are ignored.
+///
+/// Requires the `metrics` Cargo feature.
+impl HistogramFn for SyncHistogram<NotNan<f64>> {
+    fn record(&self, value: f64) {
+        self.record_many(value, 1);
+    }
+
+    fn record_many(&self, value: f64, count: usize) {
+        if let Ok(value) = NotNan::new(value) {
+            self.lock().update_many(value, count as u64);
+        }
+    }
+}
+
+/// A [`Recorder`] for the `metrics` crate which backs each histogram with an
+/// [`ExponentialDecayHistogram`].
+///
+/// Each distinct key registered through the `histogram!` macro is given its own histogram, which
+/// can be read back with [`MetricsRecorder::histogram`] or [`MetricsRecorder::snapshots`] for
+/// export. Counters and gauges are not supported and are discarded.
+///
+/// ```
+/// use exponential_decay_histogram::MetricsRecorder;
+///
+/// let recorder = MetricsRecorder::new();
+/// metrics::with_local_recorder(&recorder, || {
+///     metrics::histogram!("request_latency").record(1.5);
+/// });
+///
+/// for (key, snapshot) in recorder.snapshots() {
+///     println!("{}: {}", key, snapshot);
+/// }
+/// ```
+///
+/// Requires the `metrics` Cargo feature, and is compatible with version 0.24 of the `metrics`
+/// crate.
+pub struct MetricsRecorder {
+    builder: Builder<NotNan<f64>>,
+    histograms: Mutex<HashMap<Key, Arc<SyncHistogram<NotNan<f64>>>>>,
+}
+
+impl Default for MetricsRecorder {
+    fn default() -> MetricsRecorder {
+        MetricsRecorder::new()
+    }
+}
+
+impl fmt::Debug for MetricsRecorder {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("MetricsRecorder")
+            .field("histograms", &self.histograms)
+            .finish()
+    }
+}
+
+impl MetricsRecorder {
+    /// Returns a new recorder whose histograms use the default configuration.
+    pub fn new() -> MetricsRecorder {
+        MetricsRecorder::with_builder(ExponentialDecayHistogram::builder())
+    }
+
+    /// Returns a new recorder which creates its histograms with a builder.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the builder's configuration is invalid. See
+    /// [`MetricsRecorder::try_with_builder`] for details.
+    pub fn with_builder(builder: Builder<NotNan<f64>>) -> MetricsRecorder {
+        match MetricsRecorder::try_with_builder(builder) {
+            Ok(recorder) => recorder,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Returns a new recorder which creates its histograms with a builder, returning an error if
+    /// the builder's configuration is invalid.
+    ///
+    /// The configuration is checked up front with [`Builder::try_build`] so that registering a
+    /// histogram never fails.
+    pub fn try_with_builder(
+        builder: Builder<NotNan<f64>>,
+    ) -> Result<MetricsRecorder, BuilderError> {
+        builder.try_build()?;
+
+        Ok(MetricsRecorder {
+            builder,
+            histograms: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the histogram registered with a key, if any.
+    pub fn histogram(&self, key: &Key) -> Option<Arc<SyncHistogram<NotNan<f64>>>> {
+        self.lock().get(key).cloned()
+    }
+
+    /// Returns snapshots of all registered histograms along with their keys.
+    pub fn snapshots(&self) -> Vec<(Key, Snapshot<NotNan<f64>>)> {
+        self.lock()
+            .iter()
+            .map(|(key, histogram)| (key.clone(), histogram.snapshot()))
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Key, Arc<SyncHistogram<NotNan<f64>>>>> {
+        self.histograms.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Recorder for MetricsRecorder {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, _: &Key, _: &Metadata<'_>) -> Counter {
+        Counter::noop()
+    }
+
+    fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+        let histogram = self
+            .lock()
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(SyncHistogram::new(self.builder.build())))
+            .clone();
+
+        Histogram::from_arc(histogram)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BuilderError, ExponentialDecayHistogram, MetricsRecorder};
+    use metrics::Key;
+
+    #[test]
+    fn record() {
+        let recorder = MetricsRecorder::new();
+        metrics::with_local_recorder(&recorder, || {
+            let histogram = metrics::histogram!("latency");
+            histogram.record(1.5);
+            histogram.record(f64::NAN);
+            metrics::histogram!("latency").record(2.5);
+            metrics::histogram!("latency", "method" => "GET").record(3.5);
+            metrics::counter!("requests").increment(1);
+        });
+
+        let histogram = recorder.histogram(&Key::from_name("latency")).unwrap();
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 2);
+        assert_eq!(*snapshot.min(), 1.5);
+        assert_eq!(*snapshot.max(), 2.5);
+
+        assert_eq!(recorder.snapshots().len(), 2);
+        assert!(recorder.histogram(&Key::from_name("requests")).is_none());
+    }
+
+    #[test]
+    fn invalid_builder() {
+        let mut builder = ExponentialDecayHistogram::builder();
+        builder.size(0);
+        assert_eq!(
+            MetricsRecorder::try_with_builder(builder).unwrap_err(),
+            BuilderError::ZeroSize
+        );
+    }
+}
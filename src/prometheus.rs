@@ -0,0 +1,73 @@
+use crate::{Snapshot, Value};
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+use core::fmt::{Display, Write};
+
+impl<T> Snapshot<T>
+where
+    T: Value + Display,
+{
+    /// Renders the snapshot as a summary metric in the Prometheus text exposition format.
+    ///
+    /// A line is emitted for the value at each of the specified quantiles, followed by a
+    /// `<metric_name>_count` line. No quantile lines are emitted if the snapshot is empty.
+    ///
+    /// Requires the `prometheus` Cargo feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any quantile is not between 0 and 1 (inclusive).
+    pub fn to_prometheus(&self, metric_name: &str, quantiles: &[f64]) -> String {
+        let values = self.quantiles(quantiles);
+
+        let mut out = String::new();
+        writeln!(out, "# TYPE {} summary", metric_name).unwrap();
+
+        if !self.is_empty() {
+            for (quantile, value) in quantiles.iter().zip(values) {
+                writeln!(
+                    out,
+                    "{}{{quantile=\"{}\"}} {}",
+                    metric_name, quantile, value
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(out, "{}_count {}", metric_name, self.count()).unwrap();
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ExponentialDecayHistogram;
+
+    #[test]
+    fn to_prometheus() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(
+            histogram.snapshot().to_prometheus("latency", &[0.5, 0.99]),
+            "# TYPE latency summary\nlatency_count 0\n"
+        );
+
+        for i in 1..=4 {
+            histogram.update_at(now, i * 10);
+        }
+
+        assert_eq!(
+            histogram
+                .snapshot()
+                .to_prometheus("latency", &[0., 0.5, 0.99, 1.]),
+            "# TYPE latency summary\n\
+             latency{quantile=\"0\"} 10\n\
+             latency{quantile=\"0.5\"} 30\n\
+             latency{quantile=\"0.99\"} 40\n\
+             latency{quantile=\"1\"} 40\n\
+             latency_count 4\n"
+        );
+    }
+}
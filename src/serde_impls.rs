@@ -0,0 +1,251 @@
+use crate::{saturating_add, Clock, ExponentialDecayHistogram, SystemClock, Value, WeightedSample};
+use ordered_float::NotNan;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::sync::Arc;
+use std::time::Duration;
+
+// `Instant`s can't be serialized, so the timing fields are stored as offsets from the time at which
+// the histogram was serialized.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "ExponentialDecayHistogram")]
+struct Repr<T> {
+    values: Vec<Sample<T>>,
+    alpha: f64,
+    size: usize,
+    count: u64,
+    #[serde(default)]
+    evicted: u64,
+    #[serde(default)]
+    epoch_elapsed: Option<Duration>,
+    start_time_elapsed: Duration,
+    next_scale_time_remaining: Duration,
+    rescale_threshold: Duration,
+    #[serde(default)]
+    logical_clock: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Sample<T> {
+    priority: f64,
+    value: T,
+    weight: f64,
+}
+
+impl<T, R> Serialize for ExponentialDecayHistogram<T, R>
+where
+    T: Value + Serialize,
+    R: Rng,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let now = self.now();
+
+        let repr = Repr {
+            values: self
+                .values
+                .iter()
+                .map(|sample| Sample {
+                    priority: sample.priority.into_inner(),
+                    value: sample.value,
+                    weight: sample.weight,
+                })
+                .collect(),
+            alpha: self.alpha,
+            size: self.size,
+            count: self.count,
+            evicted: self.evicted,
+            epoch_elapsed: Some(now.saturating_duration_since(self.epoch)),
+            start_time_elapsed: now.saturating_duration_since(self.start_time),
+            next_scale_time_remaining: self.next_scale_time.saturating_duration_since(now),
+            rescale_threshold: self.rescale_threshold,
+            logical_clock: self.logical_time.is_some(),
+        };
+
+        repr.serialize(serializer)
+    }
+}
+
+/// The RNG state, clock, and filter are not persisted - a deserialized histogram is seeded with
+/// fresh entropy, uses the [`SystemClock`], and has no filter.
+impl<'de, T> Deserialize<'de> for ExponentialDecayHistogram<T>
+where
+    T: Value + Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = Repr::<T>::deserialize(deserializer)?;
+
+        if !(repr.alpha >= 0. && repr.alpha.is_finite()) {
+            return Err(de::Error::custom(
+                "histogram alpha must be non-negative and finite",
+            ));
+        }
+        if repr.size == 0 {
+            return Err(de::Error::custom("histogram size must be positive"));
+        }
+        if repr.rescale_threshold == Duration::from_secs(0) {
+            return Err(de::Error::custom(
+                "histogram rescale threshold must be positive",
+            ));
+        }
+        if repr.values.len() > repr.size {
+            return Err(de::Error::custom(
+                "histogram contains more values than its size",
+            ));
+        }
+
+        let values = repr
+            .values
+            .into_iter()
+            .map(|s| {
+                // a sample whose weight has underflowed during a rescale has a priority of 0 too
+                let underflowed = s.weight == 0. && s.priority == 0.;
+                if !(s.weight > 0. && s.weight.is_finite() || underflowed) {
+                    return Err(de::Error::custom(
+                        "histogram sample weight must be positive and finite",
+                    ));
+                }
+                if !(s.priority > 0. && s.priority.is_finite() || underflowed) {
+                    return Err(de::Error::custom(
+                        "histogram sample priority must be positive and finite",
+                    ));
+                }
+
+                Ok(WeightedSample {
+                    priority: NotNan::new(s.priority).map_err(de::Error::custom)?,
+                    value: s.value,
+                    weight: s.weight,
+                })
+            })
+            .collect::<Result<_, D::Error>>()?;
+
+        let now = SystemClock.now();
+        let start_time = now.checked_sub(repr.start_time_elapsed).unwrap_or(now);
+        let epoch = match repr.epoch_elapsed {
+            Some(elapsed) => now.checked_sub(elapsed).unwrap_or(now),
+            None => start_time,
+        };
+
+        Ok(ExponentialDecayHistogram {
+            values,
+            alpha: repr.alpha,
+            size: repr.size,
+            count: repr.count,
+            evicted: repr.evicted,
+            epoch,
+            start_time,
+            next_scale_time: saturating_add(now, repr.next_scale_time_remaining),
+            rescale_threshold: repr.rescale_threshold,
+            clock: Arc::new(SystemClock),
+            logical_time: if repr.logical_clock { Some(now) } else { None },
+            filter: None,
+            rng: SmallRng::from_rng(&mut rand::rng()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ExponentialDecayHistogram, SummaryStats};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn round_trip() {
+        let mut now = Instant::now();
+        let mut histogram = ExponentialDecayHistogram::builder()
+            .at(now)
+            .size(100)
+            .build();
+
+        for i in 0..1000 {
+            histogram.update_at(now, i);
+            now += Duration::from_millis(100);
+        }
+
+        let json = serde_json::to_string(&histogram).unwrap();
+        let deserialized = serde_json::from_str::<ExponentialDecayHistogram>(&json).unwrap();
+
+        assert_eq!(deserialized.alpha, histogram.alpha);
+        assert_eq!(deserialized.size, histogram.size);
+
+        let expected = histogram.snapshot();
+        let actual = deserialized.snapshot();
+        assert_eq!(actual.count(), expected.count());
+        assert_eq!(actual.min(), expected.min());
+        assert_eq!(actual.max(), expected.max());
+        assert_eq!(actual.mean(), expected.mean());
+        assert_eq!(actual.stddev(), expected.stddev());
+        assert_eq!(
+            actual.values().collect::<Vec<_>>(),
+            expected.values().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn summary_stats_round_trip() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        for i in 0..100 {
+            histogram.update(i);
+        }
+
+        let stats = histogram.snapshot().summary_stats(&[0.5, 0.99]);
+        assert_eq!(stats.quantiles.len(), 2);
+
+        let json = serde_json::to_string(&stats).unwrap();
+        let deserialized = serde_json::from_str::<SummaryStats>(&json).unwrap();
+        assert_eq!(deserialized, stats);
+    }
+
+    #[test]
+    fn reject_oversized() {
+        let mut histogram = ExponentialDecayHistogram::builder().size(10).build();
+        for i in 0..10 {
+            histogram.update(i);
+        }
+
+        let json = serde_json::to_string(&histogram).unwrap();
+        let json = json.replace("\"size\":10", "\"size\":5");
+        assert!(serde_json::from_str::<ExponentialDecayHistogram>(&json).is_err());
+    }
+
+    #[test]
+    fn reject_invalid_weight() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        histogram.update(1);
+
+        let json = serde_json::to_string(&histogram).unwrap();
+        let weight = format!("\"weight\":{}", histogram.values.peek().unwrap().weight);
+        assert!(json.contains(&weight));
+
+        for bad in &["-1.0", "0.0", "1e999", "null"] {
+            let json = json.replace(&weight, &format!("\"weight\":{}", bad));
+            assert!(serde_json::from_str::<ExponentialDecayHistogram>(&json).is_err());
+        }
+
+        let priority = format!("\"priority\":{}", histogram.values.peek().unwrap().priority);
+        assert!(json.contains(&priority));
+
+        for bad in &["-1.0", "0.0", "1e999", "null"] {
+            let json = json.replace(&priority, &format!("\"priority\":{}", bad));
+            assert!(serde_json::from_str::<ExponentialDecayHistogram>(&json).is_err());
+        }
+
+        // the weight of the first value underflows to 0 along with its priority
+        let mut histogram = ExponentialDecayHistogram::builder().alpha(1.).build();
+        let now = histogram.start_time();
+        histogram.update_at(now, 1);
+        histogram.update_at(now + Duration::from_secs(2 * 60 * 60), 2);
+
+        let json = serde_json::to_string(&histogram).unwrap();
+        assert!(json.contains("\"weight\":0.0"));
+        let deserialized = serde_json::from_str::<ExponentialDecayHistogram>(&json).unwrap();
+        assert_eq!(deserialized.snapshot(), histogram.snapshot());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{Snapshot, Value};
+#[cfg(not(feature = "std"))]
+use alloc::format;
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+impl<T> Snapshot<T>
+where
+    T: Value + Display,
+{
+    /// Renders the snapshot as StatsD gauge lines.
+    ///
+    /// A `<prefix>.p<quantile>:<value>|g` line is emitted for the value at each of the specified
+    /// quantiles, followed by a `<prefix>.count:<count>|g` line. Quantiles are named by their
+    /// digits after the decimal point, padded to at least two digits, so 0.5 is `p50`, 0.999 is
+    /// `p999`, and 1 is `p100`. No quantile lines are emitted if the snapshot is empty.
+    ///
+    /// Requires the `statsd` Cargo feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any quantile is not between 0 and 1 (inclusive).
+    pub fn to_statsd(&self, prefix: &str, quantiles: &[f64]) -> Vec<String> {
+        let values = self.quantiles(quantiles);
+
+        let mut lines = Vec::new();
+
+        if !self.is_empty() {
+            for (&quantile, value) in quantiles.iter().zip(values) {
+                lines.push(format!(
+                    "{}.p{}:{}|g",
+                    prefix,
+                    quantile_name(quantile),
+                    value
+                ));
+            }
+        }
+
+        lines.push(format!("{}.count:{}|g", prefix, self.count()));
+
+        lines
+    }
+}
+
+fn quantile_name(quantile: f64) -> String {
+    if quantile == 1. {
+        return "100".to_string();
+    }
+
+    let mut name = quantile.to_string().trim_start_matches("0.").to_string();
+    if name.len() < 2 {
+        name.push('0');
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ExponentialDecayHistogram;
+
+    #[test]
+    fn to_statsd() {
+        let mut histogram = ExponentialDecayHistogram::new();
+        let now = histogram.start_time;
+
+        assert_eq!(
+            histogram.snapshot().to_statsd("latency", &[0.5, 0.99]),
+            vec!["latency.count:0|g"]
+        );
+
+        for i in 1..=4 {
+            histogram.update_at(now, i * 10);
+        }
+
+        assert_eq!(
+            histogram
+                .snapshot()
+                .to_statsd("latency", &[0., 0.05, 0.5, 0.999, 1.]),
+            vec![
+                "latency.p00:10|g",
+                "latency.p05:20|g",
+                "latency.p50:30|g",
+                "latency.p999:40|g",
+                "latency.p100:40|g",
+                "latency.count:4|g",
+            ]
+        );
+    }
+}
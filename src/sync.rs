@@ -0,0 +1,109 @@
+use crate::{ExponentialDecayHistogram, Snapshot, Value};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+
+/// A thread-safe wrapper around an [`ExponentialDecayHistogram`].
+///
+/// The histogram is protected by a mutex, so all methods take `&self` and the wrapper can be
+/// shared between threads (e.g. in an `Arc`). Each method holds the lock for its duration -
+/// in particular, [`SyncHistogram::snapshot`] blocks updates while the histogram's values are
+/// copied and sorted.
+///
+/// Requires the `std` Cargo feature.
+#[derive(Debug)]
+pub struct SyncHistogram<T = i64> {
+    inner: Mutex<ExponentialDecayHistogram<T>>,
+}
+
+impl<T> Default for SyncHistogram<T>
+where
+    T: Value,
+{
+    fn default() -> SyncHistogram<T> {
+        SyncHistogram::new(ExponentialDecayHistogram::new())
+    }
+}
+
+impl<T> From<ExponentialDecayHistogram<T>> for SyncHistogram<T> {
+    fn from(histogram: ExponentialDecayHistogram<T>) -> SyncHistogram<T> {
+        SyncHistogram::new(histogram)
+    }
+}
+
+impl<T> SyncHistogram<T> {
+    /// Returns a new wrapper around a histogram.
+    pub fn new(histogram: ExponentialDecayHistogram<T>) -> SyncHistogram<T> {
+        SyncHistogram {
+            inner: Mutex::new(histogram),
+        }
+    }
+
+    /// Consumes the wrapper, returning the inner histogram.
+    pub fn into_inner(self) -> ExponentialDecayHistogram<T> {
+        self.inner.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Locks the histogram, returning a guard which can be used to access it directly.
+    pub fn lock(&self) -> MutexGuard<'_, ExponentialDecayHistogram<T>> {
+        // the histogram's state is consistent even if a thread panicked while holding the lock
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> SyncHistogram<T>
+where
+    T: Value,
+{
+    /// Inserts a value into the histogram at the current time.
+    pub fn update(&self, value: T) {
+        self.lock().update(value);
+    }
+
+    /// Inserts a value into the histogram at the specified time.
+    ///
+    /// # Panics
+    ///
+    /// May panic if values are inserted at non-monotonically increasing times.
+    pub fn update_at(&self, time: Instant, value: T) {
+        self.lock().update_at(time, value);
+    }
+
+    /// Takes a snapshot of the current state of the histogram.
+    pub fn snapshot(&self) -> Snapshot<T> {
+        self.lock().snapshot()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SyncHistogram;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn concurrent_updates() {
+        let histogram = Arc::new(SyncHistogram::<i64>::default());
+
+        let threads = (0..8)
+            .map(|i| {
+                let histogram = histogram.clone();
+                thread::spawn(move || {
+                    for j in 0..10_000 {
+                        histogram.update(i * 10_000 + j);
+                        if j % 1000 == 0 {
+                            histogram.snapshot();
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 80_000);
+        assert_eq!(snapshot.len(), 1028);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{saturating_add, ExponentialDecayHistogram, Instant, Snapshot, Value};
+use core::convert::TryFrom;
+use core::time::Duration;
+
+/// A histogram which reports statistics over fixed, non-overlapping windows of time.
+///
+/// Values are collected into the current window, and [`TumblingWindowHistogram::snapshot`]
+/// reports the statistics of the most recently completed window. This is useful to align reports
+/// with other systems which use fixed intervals, such as "the last full minute".
+///
+/// Each window is backed by an [`ExponentialDecayHistogram`] which is cleared as the window rolls
+/// over, so a window retains at most that histogram's size of values. By default the histogram's
+/// alpha is 0, so all values in a window have the same weight.
+#[derive(Debug)]
+pub struct TumblingWindowHistogram<T = i64> {
+    current: ExponentialDecayHistogram<T>,
+    previous: Snapshot<T>,
+    window: Duration,
+    window_end: Instant,
+}
+
+impl<T> TumblingWindowHistogram<T>
+where
+    T: Value,
+{
+    /// Returns a new histogram with windows of the specified length, starting at the current time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is 0.
+    pub fn new(window: Duration) -> Self {
+        Self::from_histogram(
+            ExponentialDecayHistogram::builder().alpha(0.).build(),
+            window,
+        )
+    }
+
+    /// Returns a new histogram with windows of the specified length which collects values with an
+    /// existing histogram.
+    ///
+    /// The histogram is cleared, and the first window starts at its decay landmark. Its alpha
+    /// should typically be 0 to weight all values in a window equally.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is 0.
+    pub fn from_histogram(mut histogram: ExponentialDecayHistogram<T>, window: Duration) -> Self {
+        assert!(window > Duration::from_secs(0));
+
+        let start = histogram.start_time();
+        histogram.clear_at(start);
+
+        TumblingWindowHistogram {
+            current: histogram,
+            previous: Snapshot::default(),
+            window,
+            window_end: saturating_add(start, window),
+        }
+    }
+
+    /// Returns the length of the histogram's windows.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Inserts a value into the current window at the current time.
+    pub fn update(&mut self, value: T) {
+        let now = self.current.now();
+        self.update_at(now, value);
+    }
+
+    /// Inserts a value into the window containing the specified time.
+    ///
+    /// # Panics
+    ///
+    /// May panic if values are inserted at non-monotonically increasing times.
+    pub fn update_at(&mut self, time: Instant, value: T) {
+        self.rotate(time);
+        self.current.update_at(time, value);
+    }
+
+    /// Returns a snapshot of the most recently completed window as of the current time.
+    pub fn snapshot(&mut self) -> &Snapshot<T> {
+        let now = self.current.now();
+        self.snapshot_at(now)
+    }
+
+    /// Returns a snapshot of the most recently completed window as of the specified time.
+    ///
+    /// The snapshot is empty if no window has completed yet, or if no values were inserted in the
+    /// window.
+    pub fn snapshot_at(&mut self, time: Instant) -> &Snapshot<T> {
+        self.rotate(time);
+        &self.previous
+    }
+
+    fn rotate(&mut self, time: Instant) {
+        if time < self.window_end {
+            return;
+        }
+
+        let elapsed = time - self.window_end;
+        if elapsed < self.window {
+            self.current.snapshot_into(&mut self.previous);
+        } else {
+            // the window before the one containing `time` is empty
+            self.previous = Snapshot::default();
+        }
+
+        let offset = elapsed.as_nanos() % self.window.as_nanos();
+        let offset = Duration::from_nanos(u64::try_from(offset).unwrap_or(u64::MAX));
+        let start = time.checked_sub(offset).unwrap_or(time);
+        self.current.clear_at(start);
+        self.window_end = saturating_add(start, self.window);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ExponentialDecayHistogram, TumblingWindowHistogram};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn windows() {
+        let start = Instant::now();
+        let histogram = ExponentialDecayHistogram::builder()
+            .at(start)
+            .alpha(0.)
+            .build();
+        let mut histogram =
+            TumblingWindowHistogram::from_histogram(histogram, Duration::from_secs(60));
+        let at = |secs| start + Duration::from_secs(secs);
+
+        histogram.update_at(at(10), 1);
+        histogram.update_at(at(50), 2);
+        assert!(histogram.snapshot_at(at(59)).is_empty());
+
+        histogram.update_at(at(70), 3);
+        let snapshot = histogram.snapshot_at(at(70));
+        assert_eq!(snapshot.count(), 2);
+        assert_eq!(
+            snapshot.values().map(|(v, _)| v).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        histogram.update_at(at(119), 4);
+        let snapshot = histogram.snapshot_at(at(120));
+        assert_eq!(snapshot.count(), 2);
+        assert_eq!(
+            snapshot.values().map(|(v, _)| v).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+
+        // the window from 120 to 180 was empty
+        histogram.update_at(at(200), 5);
+        assert!(histogram.snapshot_at(at(200)).is_empty());
+
+        let snapshot = histogram.snapshot_at(at(299));
+        assert_eq!(
+            snapshot.values().map(|(v, _)| v).collect::<Vec<_>>(),
+            vec![5]
+        );
+    }
+}